@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+use bevy_egui::egui::{Slider, Ui};
+use serde::{Deserialize, Serialize};
+
+use super::{Vehicle, WheelState};
+use crate::input::VehicleInput;
+
+// Lives on the body entity
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Brakes {
+	// total brake torque over all wheels at full pedal, Nm
+	pub max_torque		: f32,
+	// share of brake torque going to front wheels, rear gets the rest
+	pub front_bias		: f32,
+	// total handbrake torque over rear wheels, Nm
+	pub handbrake_torque: f32,
+}
+
+impl Default for Brakes {
+	fn default() -> Self {
+		Self {
+			max_torque		: 400.0,
+			front_bias		: 0.6,
+			handbrake_torque: 300.0,
+		}
+	}
+}
+
+pub fn brake_system(
+		input		: Res<VehicleInput>,
+		vehicles	: Query<(&Vehicle, &Brakes)>,
+	mut wheel_states: Query<&mut WheelState>,
+) {
+	for (vehicle, brakes) in vehicles.iter() {
+		let front_cnt	= vehicle.wheels.iter().filter(|w| w.front).count().max(1) as f32;
+		let rear_cnt	= vehicle.wheels.iter().filter(|w| !w.front).count().max(1) as f32;
+
+		// if one end has no wheels at all the other end takes all the brake torque
+		let has_front	= vehicle.wheels.iter().any(|w| w.front);
+		let has_rear	= vehicle.wheels.iter().any(|w| !w.front);
+		let front_bias	= match (has_front, has_rear) {
+			(true, false)	=> 1.0,
+			(false, true)	=> 0.0,
+			_				=> brakes.front_bias,
+		};
+
+		for wheel in vehicle.wheels.iter() {
+			let torque = if wheel.front {
+				input.brake * brakes.max_torque * front_bias / front_cnt
+			} else {
+				input.brake * brakes.max_torque * (1.0 - front_bias) / rear_cnt
+				+ input.handbrake * brakes.handbrake_torque / rear_cnt
+			};
+
+			if let Ok(mut state) = wheel_states.get_mut(wheel.wheel) {
+				state.brake_torque = torque;
+			}
+		}
+	}
+}
+
+pub fn draw_brakes_ui(ui: &mut Ui, brakes: &mut Brakes) {
+	ui.collapsing("Brakes".to_string(), |ui| {
+	ui.vertical(|ui| {
+
+	ui.add(Slider::new(&mut brakes.max_torque, 0.0 ..= 2000.0).text("Brake torque"));
+	ui.add(Slider::new(&mut brakes.front_bias, 0.0 ..= 1.0).text("Front bias"));
+	ui.add(Slider::new(&mut brakes.handbrake_torque, 0.0 ..= 2000.0).text("Handbrake torque"));
+
+	}); // ui.vertical
+	}); // ui.collapsing
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+// command line arguments. Parsed by hand since we only have a handful of them:
+// --vehicle <path>		vehicle description file (.ron)
+// --headless			run without window for --steps physics steps
+// --steps <n>
+// --script <path>		drive vehicle with timed commands instead of keyboard
+// --scene <name>		empty, cubes, hills, slalom or course
+// --terrain <name>		flat, hills, heightmap (.png) or terrain description file (.ron)
+// --seed <n>			seed for procedural terrain
+// --course <path>		obstacle course description file (.ron)
+#[derive(Debug)]
+pub struct CliArgs {
+	pub vehicle		: Option<PathBuf>,
+	pub headless	: bool,
+	pub steps		: u32,
+	pub script		: Option<PathBuf>,
+	pub scene		: Option<String>,
+	pub terrain		: Option<String>,
+	pub seed		: Option<u64>,
+	pub course		: Option<PathBuf>,
+}
+
+impl Default for CliArgs {
+	fn default() -> Self {
+		Self {
+			vehicle		: None,
+			headless	: false,
+			steps		: 600,
+			script		: None,
+			scene		: None,
+			terrain		: None,
+			seed		: None,
+			course		: None,
+		}
+	}
+}
+
+impl CliArgs {
+	pub fn parse() -> Self {
+		let mut out		= Self::default();
+		let mut args	= std::env::args().skip(1);
+
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"--vehicle" => out.vehicle = args.next().map(PathBuf::from),
+				"--headless" => out.headless = true,
+				"--script" => out.script = args.next().map(PathBuf::from),
+				"--steps" => match args.next().map(|s| s.parse::<u32>()) {
+					Some(Ok(steps)) => out.steps = steps,
+					_ => println!("--steps expects a number, keeping {}", out.steps),
+				},
+				"--scene" => out.scene = args.next(),
+				"--course" => out.course = args.next().map(PathBuf::from),
+				"--terrain" => out.terrain = args.next(),
+				"--seed" => match args.next().map(|s| s.parse::<u64>()) {
+					Some(Ok(seed)) => out.seed = Some(seed),
+					_ => println!("--seed expects a number"),
+				},
+				_ => println!("unknown argument {:?}", arg),
+			}
+		}
+
+		out
+	}
+}
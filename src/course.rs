@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use nalgebra::{vector, Isometry3, Vector3};
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+use crate::surface::{Surface, SurfaceRegistry};
+use crate::vehicle_desc::load_ron;
+
+// Static obstacles for testing suspension and climbing. Position is where the obstacle starts on the ground,
+// heading is rotation around vertical axis in degrees, with 0 every obstacle extends along +z.
+// Angles are in degrees, everything else is in meters
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Obstacle {
+	// slab rising from the ground to length * sin(angle) and ending with a drop
+	Ramp		{ position: Vec3, heading: f32, angle: f32, length: f32, width: f32 },
+	// rounded bump of given height and length along the way
+	SpeedBump	{ position: Vec3, heading: f32, height: f32, length: f32, width: f32 },
+	// row of small bumps
+	Washboard	{ position: Vec3, heading: f32, bumps: u32, spacing: f32, height: f32, width: f32 },
+	Stairs		{ position: Vec3, heading: f32, steps: u32, step_height: f32, step_depth: f32, width: f32 },
+	// slab tilted sideways, rising along +x
+	SideSlope	{ position: Vec3, heading: f32, angle: f32, length: f32, width: f32 },
+	// ground can't be dug into, so pothole is a hole in a raised deck of given depth. Hole size is full size by x and z,
+	// margin is how much deck there is around it
+	Pothole		{ position: Vec3, heading: f32, hole: Vec2, depth: f32, margin: f32 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CourseDesc {
+	pub surface		: Surface,
+	pub obstacles	: Vec<Obstacle>,
+}
+
+impl Default for CourseDesc {
+	fn default() -> Self {
+		Self {
+			surface		: Surface::Asphalt,
+			obstacles	: Vec::new(),
+		}
+	}
+}
+
+impl CourseDesc {
+	pub fn load(path: &Path) -> Result<Self, String> {
+		load_ron(path)
+	}
+
+	pub fn spawn(&self, registry: &SurfaceRegistry, commands: &mut Commands) -> Vec<Entity> {
+		let mut builder = CourseBuilder::new(self.surface, registry, commands);
+		for obstacle in self.obstacles.iter() {
+			builder.obstacle(obstacle);
+		}
+		builder.spawned
+	}
+}
+
+// Spawns obstacles as static colliders made of cuboids and cylinders so that debug render can draw them
+pub struct CourseBuilder<'a, 'w, 's> {
+	surface		: Surface,
+	registry	: &'a SurfaceRegistry,
+	commands	: &'a mut Commands<'w, 's>,
+	pub spawned	: Vec<Entity>,
+}
+
+impl<'a, 'w, 's> CourseBuilder<'a, 'w, 's> {
+	pub fn new(surface: Surface, registry: &'a SurfaceRegistry, commands: &'a mut Commands<'w, 's>) -> Self {
+		Self { surface, registry, commands, spawned: Vec::new() }
+	}
+
+	pub fn obstacle(&mut self, obstacle: &Obstacle) {
+		match *obstacle {
+			Obstacle::Ramp		{ position, heading, angle, length, width }					=> self.ramp(position, heading, angle, length, width),
+			Obstacle::SpeedBump	{ position, heading, height, length, width }				=> self.speed_bump(position, heading, height, length, width),
+			Obstacle::Washboard	{ position, heading, bumps, spacing, height, width }		=> self.washboard(position, heading, bumps, spacing, height, width),
+			Obstacle::Stairs	{ position, heading, steps, step_height, step_depth, width }=> self.stairs(position, heading, steps, step_height, step_depth, width),
+			Obstacle::SideSlope	{ position, heading, angle, length, width }					=> self.side_slope(position, heading, angle, length, width),
+			Obstacle::Pothole	{ position, heading, hole, depth, margin }					=> self.pothole(position, heading, hole, depth, margin),
+		}
+	}
+
+	pub fn ramp(&mut self, position: Vec3, heading: f32, angle: f32, length: f32, width: f32) {
+		let thickness	= 0.5;
+		// pitch up so that +z goes upwards, then move the slab so that the beginning of its top face is at the origin
+		let rotation	= Vector3::x() * -angle.to_radians();
+		let start		= Isometry3::new(Vector3::zeros(), rotation) * nalgebra::Point3::new(0.0, thickness / 2.0, -length / 2.0);
+		let local		= Isometry3::new(-start.coords, rotation);
+
+		self.cuboid(position, heading, local, Vec3::new(width / 2.0, thickness / 2.0, length / 2.0));
+	}
+
+	pub fn speed_bump(&mut self, position: Vec3, heading: f32, height: f32, length: f32, width: f32) {
+		// cylinder lying across the way and sunk into the ground so that only a segment of given height and length sticks out
+		let half_length	= length / 2.0;
+		let radius		= (half_length * half_length + height * height) / (2.0 * height);
+		let local		= Isometry3::new(vector![0.0, height - radius, half_length], Vector3::z() * std::f32::consts::FRAC_PI_2);
+
+		self.cylinder(position, heading, local, width / 2.0, radius);
+	}
+
+	pub fn washboard(&mut self, position: Vec3, heading: f32, bumps: u32, spacing: f32, height: f32, width: f32) {
+		let forward		= Quat::from_rotation_y(heading.to_radians()) * Vec3::Z;
+		for i in 0..bumps {
+			self.speed_bump(position + forward * spacing * i as f32, heading, height, spacing, width);
+		}
+	}
+
+	pub fn stairs(&mut self, position: Vec3, heading: f32, steps: u32, step_height: f32, step_depth: f32, width: f32) {
+		// every step is a block from the ground up so there are no gaps underneath
+		for i in 0..steps {
+			let half_height	= (i + 1) as f32 * step_height / 2.0;
+			let local		= Isometry3::translation(0.0, half_height, (i as f32 + 0.5) * step_depth);
+			self.cuboid(position, heading, local, Vec3::new(width / 2.0, half_height, step_depth / 2.0));
+		}
+	}
+
+	pub fn side_slope(&mut self, position: Vec3, heading: f32, angle: f32, length: f32, width: f32) {
+		let thickness	= 0.5;
+		// roll so that +x goes upwards, then move the slab so that the lower edge of its top face is on the ground
+		let rotation	= Vector3::z() * angle.to_radians();
+		let edge		= Isometry3::new(Vector3::zeros(), rotation) * nalgebra::Point3::new(-width / 2.0, thickness / 2.0, 0.0);
+		let local		= Isometry3::new(vector![-edge.x, -edge.y, length / 2.0 - edge.z], rotation);
+
+		self.cuboid(position, heading, local, Vec3::new(width / 2.0, thickness / 2.0, length / 2.0));
+	}
+
+	pub fn pothole(&mut self, position: Vec3, heading: f32, hole: Vec2, depth: f32, margin: f32) {
+		let half_depth	= depth / 2.0;
+		let outer		= hole / 2.0 + Vec2::splat(margin);
+		let center_z	= outer.y;
+
+		// left and right strips go along the whole deck, front and back ones fill the rest
+		for side in [-1.0, 1.0] {
+			let x		= side * (hole.x / 2.0 + margin / 2.0);
+			let local	= Isometry3::translation(x, half_depth, center_z);
+			self.cuboid(position, heading, local, Vec3::new(margin / 2.0, half_depth, outer.y));
+
+			let z		= center_z + side * (hole.y / 2.0 + margin / 2.0);
+			let local	= Isometry3::translation(0.0, half_depth, z);
+			self.cuboid(position, heading, local, Vec3::new(hole.x / 2.0, half_depth, margin / 2.0));
+		}
+	}
+
+	fn cuboid(&mut self, position: Vec3, heading: f32, local: Isometry3<Real>, half_size: Vec3) {
+		self.spawn(position, heading, local, ColliderShape::cuboid(half_size.x, half_size.y, half_size.z));
+	}
+
+	fn cylinder(&mut self, position: Vec3, heading: f32, local: Isometry3<Real>, half_height: f32, radius: f32) {
+		self.spawn(position, heading, local, ColliderShape::cylinder(half_height, radius));
+	}
+
+	fn spawn(&mut self, position: Vec3, heading: f32, local: Isometry3<Real>, shape: ColliderShape) {
+		let origin		= Isometry3::new(position.into(), Vector3::y() * heading.to_radians());
+		let material	= self.registry.get(self.surface);
+
+		let collider	= ColliderBundle {
+			shape		: shape.into(),
+			position	: (origin * local).into(),
+			material	: self.registry.collider_material(self.surface).into(),
+			..ColliderBundle::default()
+		};
+
+		let entity = self.commands
+			.spawn_bundle	(collider)
+			.insert			(ColliderDebugRender { color: material.color })
+			.insert			(ColliderPositionSync::Discrete)
+			.insert			(material)
+			.id				();
+
+		self.spawned.push(entity);
+	}
+}
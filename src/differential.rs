@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+use bevy_egui::egui::{Slider, Ui};
+use serde::{Deserialize, Serialize};
+
+use super::{Vehicle, WheelRecord, WheelState};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum DiffKind {
+	// torque is split evenly no matter how fast wheels spin
+	Open,
+	// wheels on the axle are forced to spin together
+	Locked,
+	// torque can be moved to the slower wheel, but not more than preload + lock_ratio * input torque
+	LimitedSlip,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DiffSettings {
+	pub kind			: DiffKind,
+	// Nm
+	pub preload			: f32,
+	// fraction of axle input torque
+	pub lock_ratio		: f32,
+	// Nm per rad/s of spin difference, how hard torque is moved between wheels
+	pub lock_stiffness	: f32,
+}
+
+impl Default for DiffSettings {
+	fn default() -> Self {
+		Self {
+			kind			: DiffKind::Open,
+			preload			: 5.0,
+			lock_ratio		: 0.5,
+			lock_stiffness	: 20.0,
+		}
+	}
+}
+
+pub struct AxleDifferential {
+	pub name		: String,
+	// indices into Vehicle::wheels
+	pub wheels		: Vec<usize>,
+	pub settings	: DiffSettings,
+}
+
+// one differential per axle. Lives on the body entity
+#[derive(Component, Default)]
+pub struct Differentials {
+	pub axles		: Vec<AxleDifferential>,
+}
+
+// Wheels with the same offset by z are considered to be on the same axle. Indices into wheels, axles in the order
+// their first wheel comes
+pub fn group_axles(wheels: &[WheelRecord]) -> Vec<Vec<usize>> {
+	let mut axles: Vec<Vec<usize>> = Vec::new();
+
+	for (i, wheel) in wheels.iter().enumerate() {
+		match axles.iter_mut().find(|a| (wheels[a[0]].offset.z - wheel.offset.z).abs() < 0.01) {
+			Some(axle) => axle.push(i),
+			None => axles.push(vec![i]),
+		}
+	}
+
+	axles
+}
+
+// "RF LF" for an axle with RF and LF wheels
+pub fn axle_name(wheels: &[WheelRecord], axle: &[usize]) -> String {
+	axle.iter().map(|i| wheels[*i].name.as_str()).collect::<Vec<_>>().join(" ")
+}
+
+impl Differentials {
+	// Axles take settings from per_axle in the order they are found, the ones past its end get settings
+	pub fn from_wheels(wheels: &[WheelRecord], settings: &DiffSettings, per_axle: &[DiffSettings]) -> Self {
+		let axles = group_axles(wheels).into_iter().enumerate().map(|(i, axle)| AxleDifferential {
+			name		: axle_name(wheels, &axle),
+			wheels		: axle,
+			settings	: per_axle.get(i).unwrap_or(settings).clone(),
+		}).collect();
+
+		Self { axles }
+	}
+}
+
+// Takes drive torque powertrain gave to each wheel and moves part of it from faster to slower wheels of the same axle.
+// When a wheel ends up with negative torque its motor target is flipped so that it's being held back
+pub fn differential_system(
+		vehicles	: Query<(&Vehicle, &Differentials)>,
+	mut wheel_states: Query<&mut WheelState>,
+) {
+	for (vehicle, differentials) in vehicles.iter() {
+		for axle in differentials.axles.iter() {
+			let entities: Vec<Entity> = axle.wheels.iter().map(|&i| vehicle.wheels[i].wheel).collect();
+
+			let mut spins	= Vec::with_capacity(entities.len());
+			let mut torque	= 0.0;
+			let mut target	= 0.0;
+			for &e in entities.iter() {
+				if let Ok(state) = wheel_states.get(e) {
+					spins.push(state.spin);
+					torque += state.drive_torque;
+					if state.drive_target != 0.0 { target = state.drive_target; }
+				}
+			}
+
+			// nothing to distribute or no drive direction (engine braking)
+			if spins.len() != entities.len() || spins.len() < 2 || torque <= 0.0 || target == 0.0 {
+				continue;
+			}
+
+			let max_transfer = match axle.settings.kind {
+				DiffKind::Open			=> 0.0,
+				DiffKind::Locked		=> f32::MAX,
+				DiffKind::LimitedSlip	=> axle.settings.preload + axle.settings.lock_ratio * torque,
+			};
+
+			// spin along drive direction so that "slower" means the same going forward and in reverse
+			let dir			= target.signum();
+			let mean_spin	= spins.iter().sum::<f32>() / spins.len() as f32;
+			let even_torque	= torque / spins.len() as f32;
+
+			for (&e, spin) in entities.iter().zip(spins) {
+				let transfer	= (axle.settings.lock_stiffness * dir * (mean_spin - spin)).clamp(-max_transfer * 0.5, max_transfer * 0.5);
+				let wheel_torque= even_torque + transfer;
+
+				if let Ok(mut state) = wheel_states.get_mut(e) {
+					state.drive_torque	= wheel_torque.abs();
+					state.drive_target	= if wheel_torque >= 0.0 { target } else { -target };
+				}
+			}
+		}
+	}
+}
+
+pub fn draw_differentials_ui(ui: &mut Ui, differentials: &mut Differentials) {
+	ui.collapsing("Differentials".to_string(), |ui| {
+	ui.vertical(|ui| {
+
+	for axle in differentials.axles.iter_mut() {
+		let settings = &mut axle.settings;
+
+		ui.label(axle.name.clone());
+		ui.horizontal(|ui| {
+			ui.radio_value(&mut settings.kind, DiffKind::Open, "Open");
+			ui.radio_value(&mut settings.kind, DiffKind::Locked, "Locked");
+			ui.radio_value(&mut settings.kind, DiffKind::LimitedSlip, "LSD");
+		});
+
+		if settings.kind == DiffKind::LimitedSlip {
+			ui.add(Slider::new(&mut settings.preload, 0.0 ..= 100.0).text("Preload"));
+			ui.add(Slider::new(&mut settings.lock_ratio, 0.0 ..= 1.0).text("Lock ratio"));
+		}
+
+		if settings.kind != DiffKind::Open {
+			ui.add(Slider::new(&mut settings.lock_stiffness, 1.0 ..= 100.0).text("Lock stiffness"));
+		}
+	}
+
+	}); // ui.vertical
+	}); // ui.collapsing
+}
@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+use bevy_egui::egui::{Slider, Ui};
+use serde::{Deserialize, Serialize};
+
+use super::WheelRecord;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum DriveLayout {
+	// driven wheels are taken from vehicle description
+	Custom,
+	FWD,
+	RWD,
+	AWD,
+}
+
+// Which wheels receive drive and how it's split between front and rear. Lives on the body entity next to Vehicle
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Drivetrain {
+	pub layout		: DriveLayout,
+	// share of drive going to front wheels in AWD, rear gets the rest
+	pub front_split	: f32,
+}
+
+impl Default for Drivetrain {
+	fn default() -> Self {
+		Self {
+			layout		: DriveLayout::Custom,
+			front_split	: 0.5,
+		}
+	}
+}
+
+impl Drivetrain {
+	pub fn is_driven(&self, wheel: &WheelRecord) -> bool {
+		match self.layout {
+			DriveLayout::Custom	=> wheel.driven,
+			DriveLayout::FWD	=> wheel.front,
+			DriveLayout::RWD	=> !wheel.front,
+			DriveLayout::AWD	=> true,
+		}
+	}
+
+	// fraction of total drive each wheel gets, sums up to 1 (or 0 if nothing is driven).
+	// Front and rear groups get their split which is then divided evenly between wheels of the group
+	pub fn wheel_shares(&self, wheels: &[WheelRecord]) -> Vec<f32> {
+		let driven_front	= wheels.iter().filter(|w| w.front && self.is_driven(w)).count();
+		let driven_rear		= wheels.iter().filter(|w| !w.front && self.is_driven(w)).count();
+
+		let (mut front_share, mut rear_share) = match self.layout {
+			DriveLayout::AWD => (self.front_split, 1.0 - self.front_split),
+			_ => {
+				let total = (driven_front + driven_rear).max(1) as f32;
+				(driven_front as f32 / total, driven_rear as f32 / total)
+			},
+		};
+
+		// nothing to give drive to on one end, so the other end takes it all
+		if driven_front == 0 { rear_share += front_share; front_share = 0.0; }
+		if driven_rear == 0 { front_share += rear_share; rear_share = 0.0; }
+
+		wheels.iter().map(|w| {
+			if !self.is_driven(w) {
+				0.0
+			} else if w.front {
+				front_share / driven_front as f32
+			} else {
+				rear_share / driven_rear as f32
+			}
+		}).collect()
+	}
+}
+
+pub fn draw_drivetrain_ui(ui: &mut Ui, drivetrain: &mut Drivetrain) {
+	ui.collapsing("Drivetrain".to_string(), |ui| {
+	ui.vertical(|ui| {
+
+	ui.horizontal(|ui| {
+		ui.radio_value(&mut drivetrain.layout, DriveLayout::Custom, "Custom");
+		ui.radio_value(&mut drivetrain.layout, DriveLayout::FWD, "FWD");
+		ui.radio_value(&mut drivetrain.layout, DriveLayout::RWD, "RWD");
+		ui.radio_value(&mut drivetrain.layout, DriveLayout::AWD, "AWD");
+	});
+
+	if drivetrain.layout == DriveLayout::AWD {
+		ui.add(Slider::new(&mut drivetrain.front_split, 0.0 ..= 1.0).text("Front torque split"));
+	}
+
+	}); // ui.vertical
+	}); // ui.collapsing
+}
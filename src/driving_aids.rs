@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use bevy_egui::egui::{Slider, Ui};
+use serde::{Deserialize, Serialize};
+
+use super::{Vehicle, WheelState};
+
+// Anti-lock braking and traction control. Both watch per wheel slip ratio and cut brake/drive torque
+// while it's past the threshold, then give it back gradually. Lives on the body entity
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DrivingAids {
+	pub abs				: bool,
+	// wheel is considered locking when slip ratio in travel direction goes below -abs_slip
+	pub abs_slip		: f32,
+	pub tcs				: bool,
+	// wheel is considered spinning when slip ratio in drive direction goes above tcs_slip
+	pub tcs_slip		: f32,
+	// how fast torque is cut and given back, fraction per second
+	pub release_rate	: f32,
+	pub recover_rate	: f32,
+
+	// current cut for every wheel in Vehicle::wheels order, 0 is no cut, 1 is no torque at all
+	#[serde(skip)]
+	pub abs_cut			: Vec<f32>,
+	#[serde(skip)]
+	pub tcs_cut			: Vec<f32>,
+}
+
+impl Default for DrivingAids {
+	fn default() -> Self {
+		Self {
+			abs				: true,
+			abs_slip		: 0.2,
+			tcs				: true,
+			tcs_slip		: 0.2,
+			release_rate	: 20.0,
+			recover_rate	: 5.0,
+			abs_cut			: Vec::new(),
+			tcs_cut			: Vec::new(),
+		}
+	}
+}
+
+fn modulate(cut: &mut f32, slipping: bool, aids: &DrivingAids, dt: f32) {
+	if slipping {
+		*cut = (*cut + aids.release_rate * dt).min(1.0);
+	} else {
+		*cut = (*cut - aids.recover_rate * dt).max(0.0);
+	}
+}
+
+pub fn driving_aids_system(
+		integration	: Res<IntegrationParameters>,
+	mut vehicles	: Query<(&Vehicle, &mut DrivingAids)>,
+	mut wheel_states: Query<&mut WheelState>,
+) {
+	let dt = integration.dt;
+
+	for (vehicle, mut aids) in vehicles.iter_mut() {
+		let aids = &mut *aids;
+		aids.abs_cut.resize(vehicle.wheels.len(), 0.0);
+		aids.tcs_cut.resize(vehicle.wheels.len(), 0.0);
+
+		for (i, wheel) in vehicle.wheels.iter().enumerate() {
+			let mut state = match wheel_states.get_mut(wheel.wheel) { Ok(s) => s, Err(_) => continue };
+
+			if aids.abs && state.brake_torque > 0.0 {
+				// going backwards a locked wheel has positive slip ratio
+				let locking = state.slip_ratio * state.ground_speed.signum() < -aids.abs_slip;
+				let mut cut = aids.abs_cut[i];
+				modulate(&mut cut, locking, aids, dt);
+				aids.abs_cut[i] = cut;
+				state.brake_torque *= 1.0 - cut;
+			} else {
+				aids.abs_cut[i] = 0.0;
+			}
+
+			if aids.tcs && state.drive_torque > 0.0 && state.drive_target != 0.0 {
+				let spinning = state.slip_ratio * state.drive_target.signum() > aids.tcs_slip;
+				let mut cut = aids.tcs_cut[i];
+				modulate(&mut cut, spinning, aids, dt);
+				aids.tcs_cut[i] = cut;
+				state.drive_torque *= 1.0 - cut;
+			} else {
+				aids.tcs_cut[i] = 0.0;
+			}
+		}
+	}
+}
+
+pub fn draw_driving_aids_ui(ui: &mut Ui, aids: &mut DrivingAids) {
+	ui.collapsing("Driving aids".to_string(), |ui| {
+	ui.vertical(|ui| {
+
+	let abs_active = aids.abs_cut.iter().any(|&cut| cut > 0.0);
+	let tcs_active = aids.tcs_cut.iter().any(|&cut| cut > 0.0);
+
+	ui.checkbox(&mut aids.abs, if abs_active { "ABS (active)" } else { "ABS" });
+	ui.add(Slider::new(&mut aids.abs_slip, 0.01 ..= 1.0).text("ABS slip threshold"));
+	ui.checkbox(&mut aids.tcs, if tcs_active { "TCS (active)" } else { "TCS" });
+	ui.add(Slider::new(&mut aids.tcs_slip, 0.01 ..= 1.0).text("TCS slip threshold"));
+	ui.add(Slider::new(&mut aids.release_rate, 1.0 ..= 100.0).text("Release rate"));
+	ui.add(Slider::new(&mut aids.recover_rate, 1.0 ..= 100.0).text("Recover rate"));
+
+	}); // ui.vertical
+	}); // ui.collapsing
+}
@@ -0,0 +1,250 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use bevy_egui::egui::{Slider, Ui};
+use serde::{Deserialize, Serialize};
+
+use std::f32::consts::PI;
+
+use super::{Vehicle, WheelState};
+use crate::drivetrain::Drivetrain;
+use crate::input::VehicleInput;
+
+fn rad_s_to_rpm(rad_s: f32) -> f32 { rad_s * 60.0 / (2.0 * PI) }
+fn rpm_to_rad_s(rpm: f32) -> f32 { rpm * 2.0 * PI / 60.0 }
+
+// Engine torque comes from a curve over rpm scaled by throttle. Lives on the body entity
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Engine {
+	// (rpm, torque) points sorted by rpm, linearly interpolated in between
+	pub torque_curve		: Vec<(f32, f32)>,
+	pub idle_rpm			: f32,
+	pub redline_rpm			: f32,
+	// resistive torque when throttle is released
+	pub engine_brake_torque	: f32,
+
+	#[serde(skip)]
+	pub rpm					: f32,
+	#[serde(skip)]
+	pub torque				: f32,
+}
+
+impl Default for Engine {
+	fn default() -> Self {
+		Self {
+			torque_curve		: vec![(0.0, 10.0), (1000.0, 14.0), (2500.0, 18.0), (4000.0, 20.0), (5500.0, 18.0), (6500.0, 15.0)],
+			idle_rpm			: 900.0,
+			redline_rpm			: 6500.0,
+			engine_brake_torque	: 3.0,
+			rpm					: 900.0,
+			torque				: 0.0,
+		}
+	}
+}
+
+impl Engine {
+	pub fn max_torque_at(&self, rpm: f32) -> f32 {
+		let curve = &self.torque_curve;
+		if curve.is_empty() {
+			return 0.0;
+		}
+
+		if rpm <= curve[0].0 {
+			return curve[0].1;
+		}
+
+		for pair in curve.windows(2) {
+			let ((rpm0, torque0), (rpm1, torque1)) = (pair[0], pair[1]);
+			if rpm <= rpm1 {
+				let t = if rpm1 > rpm0 { (rpm - rpm0) / (rpm1 - rpm0) } else { 1.0 };
+				return torque0 + (torque1 - torque0) * t;
+			}
+		}
+
+		curve[curve.len() - 1].1
+	}
+}
+
+// Gear 0 is neutral, -1 is reverse, 1..=ratios.len() are forward gears. Lives on the body entity
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Gearbox {
+	pub ratios			: Vec<f32>,
+	pub reverse_ratio	: f32,
+	pub final_drive		: f32,
+	pub automatic		: bool,
+	pub shift_up_rpm	: f32,
+	pub shift_down_rpm	: f32,
+	// clutch is disengaged for this long while automatic gearbox shifts
+	pub shift_time		: f32,
+
+	#[serde(skip)]
+	pub gear			: i32,
+	#[serde(skip)]
+	pub shift_timer		: f32,
+	// 1 is fully engaged
+	#[serde(skip)]
+	pub clutch			: f32,
+}
+
+impl Default for Gearbox {
+	fn default() -> Self {
+		Self {
+			ratios			: vec![3.5, 2.2, 1.5, 1.1, 0.9],
+			reverse_ratio	: 3.2,
+			final_drive		: 8.0,
+			automatic		: true,
+			shift_up_rpm	: 5800.0,
+			shift_down_rpm	: 2200.0,
+			shift_time		: 0.3,
+			gear			: 1,
+			shift_timer		: 0.0,
+			clutch			: 1.0,
+		}
+	}
+}
+
+impl Gearbox {
+	// total ratio between engine and wheels, negative in reverse, 0 in neutral
+	pub fn total_ratio(&self) -> f32 {
+		let ratio = match self.gear {
+			g if g < 0	=> -self.reverse_ratio,
+			0			=> 0.0,
+			g			=> self.ratios.get(g as usize - 1).copied().unwrap_or(0.0),
+		};
+
+		ratio * self.final_drive
+	}
+
+	pub fn top_gear(&self) -> i32 {
+		self.ratios.len() as i32
+	}
+
+	pub fn gear_name(&self) -> String {
+		match self.gear {
+			g if g < 0	=> "R".to_string(),
+			0			=> "N".to_string(),
+			g			=> g.to_string(),
+		}
+	}
+
+	fn shift(&mut self, gear: i32) {
+		let gear = gear.clamp(-1, self.top_gear());
+		if gear != self.gear {
+			self.gear			= gear;
+			self.shift_timer	= self.shift_time;
+		}
+	}
+}
+
+// Turns throttle into drive torque on wheels: wheels spin the engine through gearbox and clutch,
+// engine torque at that rpm goes back through gearbox and gets split between driven wheels.
+// Wheel joint motors are then driven towards the speed engine would have at redline, limited by that torque
+pub fn powertrain_system(
+		integration	: Res<IntegrationParameters>,
+		input		: Res<VehicleInput>,
+	mut vehicles	: Query<(&Vehicle, &Drivetrain, &mut Engine, &mut Gearbox)>,
+	mut wheel_states: Query<&mut WheelState>,
+) {
+	let dt = integration.dt;
+
+	for (vehicle, drivetrain, mut engine, mut gearbox) in vehicles.iter_mut() {
+		let shares		= drivetrain.wheel_shares(&vehicle.wheels);
+
+		// how fast driven wheels spin on average, this is what gearbox output shaft sees
+		let mut spin	= 0.0;
+		for (wheel, share) in vehicle.wheels.iter().zip(shares.iter()) {
+			if let Ok(state) = wheel_states.get(wheel.wheel) {
+				spin += state.spin * share;
+			}
+		}
+
+		gearbox.shift_timer = (gearbox.shift_timer - dt).max(0.0);
+
+		let pedal;
+		if gearbox.automatic {
+			// pick direction by throttle when almost stopped, then shift by rpm
+			if input.throttle < 0.0 && gearbox.gear >= 0 && spin < 1.0 {
+				gearbox.shift(-1);
+			} else if input.throttle > 0.0 && gearbox.gear <= 0 && spin > -1.0 {
+				gearbox.shift(1);
+			} else if gearbox.gear >= 1 && gearbox.shift_timer <= 0.0 {
+				let gearbox_rpm = rad_s_to_rpm((spin * gearbox.total_ratio()).abs());
+				if gearbox_rpm > gearbox.shift_up_rpm && gearbox.gear < gearbox.top_gear() {
+					let gear = gearbox.gear + 1;
+					gearbox.shift(gear);
+				} else if gearbox_rpm < gearbox.shift_down_rpm && gearbox.gear > 1 {
+					let gear = gearbox.gear - 1;
+					gearbox.shift(gear);
+				}
+			}
+
+			pedal = if gearbox.gear < 0 { (-input.throttle).max(0.0) } else { input.throttle.max(0.0) };
+			gearbox.clutch = if gearbox.shift_timer > 0.0 { 0.0 } else { 1.0 };
+		} else {
+			if input.shift != 0 {
+				let gear = gearbox.gear + input.shift;
+				gearbox.shift(gear);
+				gearbox.shift_timer = 0.0;
+			}
+
+			// direction comes from selected gear
+			pedal = input.throttle.abs();
+			gearbox.clutch = 1.0 - input.clutch;
+		}
+
+		let ratio		= gearbox.total_ratio();
+		let clutch		= if ratio == 0.0 { 0.0 } else { gearbox.clutch };
+
+		// engine can't go below idle, clutch slips instead. With clutch disengaged engine revs freely with throttle
+		let gearbox_rpm	= rad_s_to_rpm((spin * ratio).abs()).max(engine.idle_rpm);
+		let free_rpm	= engine.idle_rpm + pedal * (engine.redline_rpm - engine.idle_rpm);
+		engine.rpm		= clutch * gearbox_rpm + (1.0 - clutch) * free_rpm;
+
+		// rev limiter
+		engine.torque	= if engine.rpm >= engine.redline_rpm { 0.0 } else { engine.max_torque_at(engine.rpm) * pedal };
+
+		let (wheel_torque, target_speed) =
+		if ratio == 0.0 {
+			(0.0, 0.0)
+		} else if pedal > 0.0 {
+			(engine.torque * ratio.abs() * clutch, rpm_to_rad_s(engine.redline_rpm) / ratio)
+		} else {
+			(engine.engine_brake_torque * ratio.abs() * clutch, 0.0)
+		};
+
+		for (wheel, share) in vehicle.wheels.iter().zip(shares.iter()) {
+			if let Ok(mut state) = wheel_states.get_mut(wheel.wheel) {
+				state.drive_torque	= wheel_torque * share;
+				state.drive_target	= if *share > 0.0 { target_speed } else { 0.0 };
+			}
+		}
+	}
+}
+
+pub fn draw_engine_ui(ui: &mut Ui, engine: &mut Engine, gearbox: &mut Gearbox) {
+	ui.collapsing("Engine".to_string(), |ui| {
+	ui.vertical(|ui| {
+
+	ui.label(format!("{:.0} rpm {:.1} Nm gear {}", engine.rpm, engine.torque, gearbox.gear_name()));
+
+	ui.add(Slider::new(&mut engine.idle_rpm, 300.0 ..= 2000.0).text("Idle rpm"));
+	ui.add(Slider::new(&mut engine.redline_rpm, 2000.0 ..= 12000.0).text("Redline rpm"));
+	ui.add(Slider::new(&mut engine.engine_brake_torque, 0.0 ..= 20.0).text("Engine brake torque"));
+
+	ui.checkbox(&mut gearbox.automatic, "Automatic");
+	if gearbox.automatic {
+		ui.add(Slider::new(&mut gearbox.shift_up_rpm, 1000.0 ..= 12000.0).text("Shift up rpm"));
+		ui.add(Slider::new(&mut gearbox.shift_down_rpm, 500.0 ..= 8000.0).text("Shift down rpm"));
+		ui.add(Slider::new(&mut gearbox.shift_time, 0.0 ..= 1.0).text("Shift time"));
+	}
+
+	ui.add(Slider::new(&mut gearbox.final_drive, 1.0 ..= 20.0).text("Final drive"));
+	ui.add(Slider::new(&mut gearbox.reverse_ratio, 0.5 ..= 6.0).text("Reverse ratio"));
+	for (i, ratio) in gearbox.ratios.iter_mut().enumerate() {
+		ui.add(Slider::new(ratio, 0.3 ..= 6.0).text(format!("Gear {} ratio", i + 1)));
+	}
+
+	}); // ui.vertical
+	}); // ui.collapsing
+}
@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use bevy::app::{AppExit, ScheduleRunnerSettings};
+use bevy_rapier3d::prelude::*;
+
+use std::time::Duration;
+
+use super::{Game, add_physics_plugin, setup_physics_system};
+
+// Runs simulation without window, rendering, egui and camera. Every app update advances physics by one step
+// so results don't depend on how fast the machine is.
+pub struct HeadlessRunner {
+	pub step		: u32,
+	pub max_steps	: u32,
+}
+
+pub fn setup_app(app: &mut App, max_steps: u32) {
+	app
+		.insert_resource(HeadlessRunner { step: 0, max_steps })
+		// no vsync to wait for, so run updates back to back
+		.insert_resource(ScheduleRunnerSettings::run_loop(Duration::ZERO))
+		.add_plugins(MinimalPlugins)
+		.add_plugin(TransformPlugin)
+		.add_startup_system(setup_physics_system)
+		.add_startup_system(fixed_timestep_system)
+		.add_system_to_stage(CoreStage::Last, headless_step_system);
+
+	add_physics_plugin(app);
+
+	println!("running headless for {} steps", max_steps);
+}
+
+// one physics step of IntegrationParameters::dt per update no matter how long the update took, scripts rely on it too
+fn fixed_timestep_system(mut configuration: ResMut<RapierConfiguration>) {
+	configuration.timestep_mode = TimestepMode::FixedTimestep;
+}
+
+fn headless_step_system(
+	mut runner		: ResMut<HeadlessRunner>,
+		game		: Res<Game>,
+		bodies		: Query<&RigidBodyPositionComponent>,
+	mut exit		: EventWriter<AppExit>,
+) {
+	runner.step += 1;
+
+	if runner.step < runner.max_steps {
+		return;
+	}
+
+	if let Some(body) = game.body {
+		if let Ok(pos) = bodies.get(body) {
+			println!("step {} body position {:?}", runner.step, pos.position.translation.vector);
+		}
+	}
+
+	exit.send(AppExit);
+}
@@ -0,0 +1,320 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use bevy_egui::EguiContext;
+use bevy_egui::egui::{Slider, Ui};
+use serde::{Deserialize, Serialize};
+
+use std::path::Path;
+
+use crate::vehicle_desc::read_file;
+
+// What the driver wants from the vehicle regardless of where it comes from.
+// throttle and steer are in [-1, 1] (negative throttle is reverse, positive steer is left), brake, handbrake and clutch are in [0, 1].
+// Brake only ever slows wheels down, going backwards is done with negative throttle.
+// shift is a gear change requested this frame for manual gearbox
+#[derive(Default, Clone, Copy, Debug)]
+pub struct VehicleInput {
+	pub throttle	: f32,
+	pub brake		: f32,
+	pub handbrake	: f32,
+	pub steer		: f32,
+	pub clutch		: f32,
+	pub shift		: i32,
+}
+
+// How fast throttle and steering follow keys/gamepad, in units per second. "return" rates are used when value goes back to 0
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct InputConfig {
+	pub throttle_rate		: f32,
+	pub throttle_return_rate: f32,
+	pub steer_rate			: f32,
+	pub steer_return_rate	: f32,
+	pub gamepad_deadzone	: f32,
+}
+
+impl Default for InputConfig {
+	fn default() -> Self {
+		Self {
+			throttle_rate		: 2.0,
+			throttle_return_rate: 4.0,
+			steer_rate			: 2.5,
+			steer_return_rate	: 5.0,
+			gamepad_deadzone	: 0.1,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Control {
+	Throttle,
+	Brake,
+	Handbrake,
+	Steer,
+	Clutch,
+	Shift,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ScriptCommand {
+	pub time		: f32,
+	pub control		: Control,
+	pub value		: f32,
+}
+
+// Timed commands like "t=0.0 throttle 1.0; t=2.5 steer -0.5". Commands can be separated by ';' or new lines,
+// lines starting with '#' are comments. Time is simulation time so runs are reproducible no matter the framerate.
+pub struct InputScript {
+	pub commands	: Vec<ScriptCommand>,
+	pub next		: usize,
+	pub elapsed		: f32,
+}
+
+impl InputScript {
+	pub fn parse(text: &str) -> Result<Self, String> {
+		let mut commands = Vec::new();
+
+		for entry in text.split(|c| c == ';' || c == '\n').map(|e| e.trim()) {
+			if entry.is_empty() || entry.starts_with('#') {
+				continue;
+			}
+
+			let tokens: Vec<&str> = entry.split_whitespace().collect();
+			if tokens.len() != 3 {
+				return Err(format!("expected \"t=<seconds> <control> <value>\", got \"{}\"", entry));
+			}
+
+			let time = tokens[0].strip_prefix("t=")
+				.and_then(|t| t.parse::<f32>().ok())
+				.ok_or_else(|| format!("bad time in \"{}\"", entry))?;
+
+			// NaN would break sorting and commands in the past or at infinity would never make sense
+			if !time.is_finite() || time < 0.0 {
+				return Err(format!("time has to be a finite number of seconds from start in \"{}\"", entry));
+			}
+
+			let control = match tokens[1] {
+				"throttle"	=> Control::Throttle,
+				"brake"		=> Control::Brake,
+				"handbrake"	=> Control::Handbrake,
+				"steer"		=> Control::Steer,
+				"clutch"	=> Control::Clutch,
+				"shift"		=> Control::Shift,
+				_ => return Err(format!("unknown control \"{}\" in \"{}\"", tokens[1], entry)),
+			};
+
+			// clamp lets NaN through and it would end up in joint motors
+			let value = tokens[2].parse::<f32>().ok()
+				.filter(|v| v.is_finite())
+				.ok_or_else(|| format!("bad value in \"{}\"", entry))?;
+
+			commands.push(ScriptCommand { time, control, value });
+		}
+
+		commands.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+		Ok(Self { commands, next: 0, elapsed: 0.0 })
+	}
+
+	pub fn load(path: &Path) -> Result<Self, String> {
+		let text = read_file(path)?;
+		Self::parse(&text).map_err(|e| format!("failed to parse {:?}: {}", path, e))
+	}
+}
+
+// Script drives the vehicle if given and loaded, keyboard otherwise. Keyboard is only available with a window.
+pub fn setup_input(app: &mut App, script_path: Option<&Path>, keyboard: bool) {
+	app
+		.init_resource::<VehicleInput>()
+		.init_resource::<InputConfig>();
+
+	let script = script_path.and_then(|path| match InputScript::load(path) {
+		Ok(script) => {
+			println!("driving vehicle with script {:?}", path);
+			Some(script)
+		},
+		Err(err) => {
+			println!("{}", err);
+			None
+		},
+	});
+
+	if let Some(script) = script {
+		app
+			.insert_resource(script)
+			.add_system(script_input_system.label("vehicle_input"));
+	} else if keyboard {
+		app.add_system(manual_input_system.label("vehicle_input"));
+	}
+}
+
+// moves current value towards target not faster than rate allows. Going back to 0 or crossing it uses return_rate
+fn ramp(current: f32, target: f32, rate: f32, return_rate: f32, dt: f32) -> f32 {
+	let returning	= target.abs() < current.abs() || target * current < 0.0;
+	let max_delta	= if returning { return_rate } else { rate } * dt;
+
+	current + (target - current).clamp(-max_delta, max_delta)
+}
+
+// keyboard gives digital targets, gamepad analog ones. Whichever gamepad stick/trigger is outside of deadzone wins over keyboard
+fn manual_input_system(
+		time			: Res<Time>,
+		key				: Res<Input<KeyCode>>,
+		gamepads		: Res<Gamepads>,
+		gamepad_axes	: Res<Axis<GamepadAxis>>,
+		gamepad_buttons	: Res<Axis<GamepadButton>>,
+		gamepad_presses	: Res<Input<GamepadButton>>,
+		config			: Res<InputConfig>,
+		ui_context		: Option<ResMut<EguiContext>>,
+	mut input			: ResMut<VehicleInput>,
+) {
+	// keys typed into ui text fields are not for driving
+	let typing			= match ui_context {
+		Some(mut ui_context)=> ui_context.ctx_mut().wants_keyboard_input(),
+		None				=> false,
+	};
+	let pressed			= |code: KeyCode| !typing && key.pressed(code);
+	let just_pressed	= |code: KeyCode| !typing && key.just_pressed(code);
+
+	let axis = |positive: KeyCode, negative: KeyCode| {
+		let mut value = 0.0;
+		if pressed(positive) { value += 1.0; }
+		if pressed(negative) { value -= 1.0; }
+		value
+	};
+
+	let mut throttle_target	= axis(KeyCode::W, KeyCode::R);
+	let mut steer_target	= axis(KeyCode::A, KeyCode::D);
+	let mut brake			= if pressed(KeyCode::S) { 1.0 } else { 0.0 };
+	let mut handbrake		= if pressed(KeyCode::Space) && !pressed(KeyCode::LControl) { 1.0 } else { 0.0 };
+
+	let mut shift			= just_pressed(KeyCode::E) as i32 - just_pressed(KeyCode::Q) as i32;
+	let mut clutch			= if pressed(KeyCode::C) { 1.0 } else { 0.0 };
+
+	let deadzone = |value: f32| if value.abs() > config.gamepad_deadzone { Some(value) } else { None };
+
+	for &gamepad in gamepads.iter() {
+		let stick_x	= gamepad_axes.get(GamepadAxis(gamepad, GamepadAxisType::LeftStickX)).and_then(deadzone);
+		let gas		= gamepad_buttons.get(GamepadButton(gamepad, GamepadButtonType::RightTrigger2)).and_then(deadzone);
+		let pedal	= gamepad_buttons.get(GamepadButton(gamepad, GamepadButtonType::LeftTrigger2)).and_then(deadzone);
+		// gas drives backwards while North is held
+		let reverse	= gamepad_presses.pressed(GamepadButton(gamepad, GamepadButtonType::North));
+
+		// stick to the right is positive while positive steer is left
+		if let Some(stick_x) = stick_x {
+			steer_target = -stick_x;
+		}
+
+		if let Some(gas) = gas {
+			throttle_target = if reverse { -gas } else { gas };
+		}
+
+		if let Some(pedal) = pedal {
+			brake = pedal;
+		}
+
+		if gamepad_presses.pressed(GamepadButton(gamepad, GamepadButtonType::South)) {
+			handbrake = 1.0;
+		}
+
+		if let Some(pedal) = gamepad_buttons.get(GamepadButton(gamepad, GamepadButtonType::West)).and_then(deadzone) {
+			clutch = pedal;
+		}
+
+		if gamepad_presses.just_pressed(GamepadButton(gamepad, GamepadButtonType::RightTrigger)) { shift += 1; }
+		if gamepad_presses.just_pressed(GamepadButton(gamepad, GamepadButtonType::LeftTrigger)) { shift -= 1; }
+	}
+
+	let dt			= time.delta_seconds();
+	input.throttle	= ramp(input.throttle, throttle_target, config.throttle_rate, config.throttle_return_rate, dt);
+	input.steer		= ramp(input.steer, steer_target, config.steer_rate, config.steer_return_rate, dt);
+	input.brake		= brake;
+	input.handbrake	= handbrake;
+	input.clutch	= clutch;
+	input.shift		= shift;
+}
+
+fn script_input_system(
+		integration	: Res<IntegrationParameters>,
+	mut script		: ResMut<InputScript>,
+	mut input		: ResMut<VehicleInput>,
+) {
+	let script		= &mut *script;
+	script.elapsed += integration.dt;
+
+	// shift is an event, not a state
+	input.shift		= 0;
+
+	while let Some(command) = script.commands.get(script.next) {
+		if command.time > script.elapsed {
+			break;
+		}
+
+		match command.control {
+			Control::Throttle	=> input.throttle	= command.value.clamp(-1.0, 1.0),
+			Control::Brake		=> input.brake		= command.value.clamp( 0.0, 1.0),
+			Control::Handbrake	=> input.handbrake	= command.value.clamp( 0.0, 1.0),
+			Control::Steer		=> input.steer		= command.value.clamp(-1.0, 1.0),
+			Control::Clutch		=> input.clutch		= command.value.clamp( 0.0, 1.0),
+			Control::Shift		=> input.shift		+= command.value.round() as i32,
+		}
+
+		script.next += 1;
+	}
+}
+
+pub fn draw_input_config_ui(ui: &mut Ui, config: &mut InputConfig) {
+	ui.collapsing("Input".to_string(), |ui| {
+	ui.vertical(|ui| {
+
+	ui.add(Slider::new(&mut config.throttle_rate, 0.1 ..= 20.0).text("Throttle rate"));
+	ui.add(Slider::new(&mut config.throttle_return_rate, 0.1 ..= 20.0).text("Throttle return rate"));
+	ui.add(Slider::new(&mut config.steer_rate, 0.1 ..= 20.0).text("Steer rate"));
+	ui.add(Slider::new(&mut config.steer_return_rate, 0.1 ..= 20.0).text("Steer return rate"));
+	ui.add(Slider::new(&mut config.gamepad_deadzone, 0.0 ..= 0.5).text("Gamepad deadzone"));
+
+	}); // ui.vertical
+	}); // ui.collapsing
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_commands_separated_by_semicolons_and_lines() {
+		let script = InputScript::parse("# warm up\nt=0.0 throttle 1.0; t=2.5 steer -0.5\n\nt=3 shift 1").unwrap();
+
+		assert_eq!(script.commands.len(), 3);
+		assert_eq!(script.commands[0].control, Control::Throttle);
+		assert_eq!(script.commands[1].time, 2.5);
+		assert_eq!(script.commands[1].value, -0.5);
+		assert_eq!(script.commands[2].control, Control::Shift);
+	}
+
+	#[test]
+	fn sorts_commands_by_time() {
+		let script = InputScript::parse("t=5 brake 1; t=1 throttle 1; t=3 steer 0.5").unwrap();
+		let times: Vec<f32> = script.commands.iter().map(|c| c.time).collect();
+
+		assert_eq!(times, vec![1.0, 3.0, 5.0]);
+		assert_eq!(script.commands[0].control, Control::Throttle);
+	}
+
+	#[test]
+	fn rejects_malformed_commands() {
+		assert!(InputScript::parse("t=1 throttle").is_err());
+		assert!(InputScript::parse("1 throttle 1").is_err());
+		assert!(InputScript::parse("t=1 nitro 1").is_err());
+		assert!(InputScript::parse("t=1 throttle full").is_err());
+		assert!(InputScript::parse("t=1 throttle NaN").is_err());
+		assert!(InputScript::parse("t=1 steer inf").is_err());
+	}
+
+	#[test]
+	fn rejects_bad_times() {
+		assert!(InputScript::parse("t=-1 throttle 1").is_err());
+		assert!(InputScript::parse("t=NaN throttle 1").is_err());
+		assert!(InputScript::parse("t=inf throttle 1").is_err());
+	}
+}
@@ -8,6 +8,42 @@ use nalg::vector;
 
 use bevy::render::mesh::shape as render_shape;
 
+use std::ops::RangeInclusive;
+
+mod brakes;
+mod cli;
+mod course;
+mod differential;
+mod driving_aids;
+mod drivetrain;
+mod engine;
+mod headless;
+mod input;
+mod presets;
+mod reset;
+mod scene;
+mod steering;
+mod surface;
+mod terrain;
+mod tire;
+mod vehicle_desc;
+
+use brakes::Brakes;
+use cli::CliArgs;
+use course::CourseDesc;
+use differential::Differentials;
+use driving_aids::DrivingAids;
+use drivetrain::Drivetrain;
+use engine::{Engine, Gearbox};
+use input::{VehicleInput, InputConfig};
+use presets::PresetLibrary;
+use reset::{ResetRequest, SpawnPoint};
+use scene::{ActiveScene, Scene};
+use surface::{Surface, SurfaceRegistry};
+use terrain::TerrainDesc;
+use tire::{Tire, ContactQuery};
+use vehicle_desc::*;
+
 #[derive(Component)]
 pub struct NameComponent {
 	pub name		: String
@@ -20,7 +56,7 @@ pub struct AxleTag;
 #[derive(Component)]
 pub struct BodyTag;
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
 pub enum Tag {
 	FrontWheel,
 	RearWheel,
@@ -32,21 +68,52 @@ pub enum Tag {
 pub struct Game {
 	  camera		: Option<Entity>
 	, body 			: Option<Entity>
+}
 
-	, rf_axle_joint	: Option<Entity>
-	, lf_axle_joint	: Option<Entity>
-	, rr_axle_joint	: Option<Entity>
-	, lr_axle_joint	: Option<Entity>
+// everything attached to the body for a single wheel:
+// body -> suspension joint -> suspension -> axle joint -> axle -> wheel joint -> wheel
+pub struct WheelRecord {
+	  name			: String
+	, suspension	: Entity
+	, suspension_joint : Entity
+	, axle			: Entity
+	, axle_joint	: Entity
+	, wheel_joint	: Entity
+	, wheel			: Entity
+
+	// axle placement relative to body center
+	, offset		: Vec3
+
+	, front			: bool
+	, steered		: bool
+	, driven		: bool
+}
 
-	, rf_wheel_joint: Option<Entity>
-	, lf_wheel_joint: Option<Entity>
-	, rr_wheel_joint: Option<Entity>
-	, lr_wheel_joint: Option<Entity>
+// per wheel simulation state, lives on the wheel entity
+#[derive(Component, Default)]
+pub struct WheelState {
+	// angular velocity around axle in rad/s, positive is forward
+	  spin			: f32
+	// wheel joint motor is driven towards drive_target (rad/s) with at most drive_torque
+	, drive_target	: f32
+	, drive_torque	: f32
+	// resistive torque stopping the wheel
+	, brake_torque	: f32
+
+	// chassis velocity at contact point along wheel's forward direction, m/s
+	, ground_speed	: f32
+	// (wheel surface speed - ground speed) / ground speed. Negative when wheel locks, positive when it spins
+	, slip_ratio	: f32
+	// angle between where the wheel points and where its contact point moves, radians
+	, slip_angle	: f32
+	// what wheel is rolling on, None when in the air
+	, surface		: Option<Surface>
+}
 
-	, rf_wheel		: Option<Entity>
-	, lf_wheel		: Option<Entity>
-	, rr_wheel		: Option<Entity>
-	, lr_wheel		: Option<Entity>
+// lives on the body entity
+#[derive(Component, Default)]
+pub struct Vehicle {
+	  wheels		: Vec<WheelRecord>
 }
 
 pub struct VehicleConfig {
@@ -54,6 +121,26 @@ pub struct VehicleConfig {
 	, front_r		: f32
 	, rear_hh		: f32
 	, rear_r		: f32
+
+	, susp_stiffness	: f32
+	, susp_damping		: f32
+	, susp_rest_length	: f32
+	, susp_travel		: f32
+
+	// 0% parallel steering, 100% ideal Ackermann, negative for anti-Ackermann
+	, ackermann_percent	: f32
+
+	// wheel offsets are scaled along with body when it's resized
+	, body_half_size	: Vec3
+	, body_density		: f32
+	// offset from body center
+	, body_center_of_mass: Vec3
+	// None to compute from density and size
+	, body_mass			: Option<f32>
+	, body_inertia		: Option<Vec3>
+	, axle_half_size	: Vec3
+	// distance between axle and wheel by x axis
+	, wheel_offset		: f32
 }
 
 impl Default for VehicleConfig {
@@ -63,22 +150,308 @@ impl Default for VehicleConfig {
             front_r: 0.8,
             rear_hh: 0.5,
 			rear_r: 0.8, 
+
+			susp_stiffness: 100.0,
+			susp_damping: 10.0,
+			susp_rest_length: 0.3,
+			susp_travel: 0.2,
+
+			ackermann_percent: 100.0,
+
+			body_half_size: Vec3::new(0.5, 0.5, 1.0),
+			body_density: 10.0,
+			body_center_of_mass: Vec3::ZERO,
+			body_mass: None,
+			body_inertia: None,
+			axle_half_size: Vec3::new(0.1, 0.2, 0.1),
+			wheel_offset: 0.8,
         }
     }
 }
 
+impl VehicleConfig {
+	// sliders start from whatever first front/rear wheel in description has
+	fn from_desc(desc: &VehicleDesc) -> Self {
+		let mut cfg = Self::default();
+		if let Some(front) = desc.first_wheel(true) {
+			cfg.front_hh	= front.half_width;
+			cfg.front_r		= front.radius;
+		}
+		if let Some(rear) = desc.first_wheel(false) {
+			cfg.rear_hh		= rear.half_width;
+			cfg.rear_r		= rear.radius;
+		}
+
+		cfg.susp_stiffness	= desc.joints.suspension_stiffness;
+		cfg.susp_damping	= desc.joints.suspension_damping;
+		cfg.susp_rest_length= desc.joints.suspension_rest_length;
+		cfg.susp_travel		= desc.joints.suspension_travel;
+
+		cfg.ackermann_percent = desc.joints.ackermann_percent;
+
+		cfg.body_half_size	= desc.body.half_size;
+		cfg.body_density	= desc.body.density;
+		cfg.body_center_of_mass = desc.body.center_of_mass;
+		cfg.body_mass		= desc.body.mass;
+		cfg.body_inertia	= desc.body.inertia;
+		cfg.axle_half_size	= desc.axle.half_size;
+		cfg.wheel_offset	= desc.axle.wheel_offset;
+		cfg
+	}
+
+	// reverse of from_desc: description with wheel geometry and suspension as sliders have it now
+	fn apply_to_desc(&self, desc: &VehicleDesc) -> VehicleDesc {
+		let mut out = desc.clone();
+		for wheel in out.wheels.iter_mut() {
+			if wheel.front {
+				wheel.half_width	= self.front_hh;
+				wheel.radius		= self.front_r;
+			} else {
+				wheel.half_width	= self.rear_hh;
+				wheel.radius		= self.rear_r;
+			}
+		}
+
+		out.joints.suspension_stiffness	= self.susp_stiffness;
+		out.joints.suspension_damping	= self.susp_damping;
+		out.joints.suspension_rest_length= self.susp_rest_length;
+		out.joints.suspension_travel	= self.susp_travel;
+
+		out.joints.ackermann_percent	= self.ackermann_percent;
+
+		out.body.half_size		= self.body_half_size;
+		out.body.density		= self.body_density;
+		out.body.center_of_mass	= self.body_center_of_mass;
+		out.body.mass			= self.body_mass;
+		out.body.inertia		= self.body_inertia;
+		out.axle.half_size		= self.axle_half_size;
+		out.axle.wheel_offset	= self.wheel_offset;
+		out
+	}
+
+	fn body_mass_properties(&self) -> MassProperties {
+		vehicle_desc::body_mass_properties(self.body_half_size, self.body_density, self.body_center_of_mass, self.body_mass, self.body_inertia)
+	}
+}
+
+// joint motor parameters, applied to joints as soon as they are changed
+pub struct DrivingConfig {
+	// non steered axles are held straight with these
+	  axle_stiffness	: f32
+	, axle_damping		: f32
+
+	, steer_angle		: f32
+	, steer_stiffness	: f32
+	, steer_damping		: f32
+
+	, drive_factor		: f32
+}
+
+impl DrivingConfig {
+	fn from_desc(desc: &JointsDesc) -> Self {
+		Self {
+			axle_stiffness	: desc.axle_stiffness,
+			axle_damping	: desc.axle_damping,
+			steer_angle		: desc.steer_angle,
+			steer_stiffness	: desc.steer_stiffness,
+			steer_damping	: desc.steer_damping,
+			drive_factor	: desc.drive_factor,
+		}
+	}
+
+	fn apply_to_desc(&self, desc: &mut JointsDesc) {
+		desc.axle_stiffness	= self.axle_stiffness;
+		desc.axle_damping	= self.axle_damping;
+		desc.steer_angle	= self.steer_angle;
+		desc.steer_stiffness= self.steer_stiffness;
+		desc.steer_damping	= self.steer_damping;
+		desc.drive_factor	= self.drive_factor;
+	}
+}
+
+// what capture_vehicle_desc reads from body, axles and wheels
+pub type PartQuery<'a> = (&'a ColliderShapeComponent, &'a ColliderMassPropsComponent, &'a ColliderMaterialComponent, Option<&'a Tire>);
+
+// density and explicit mass of a part collider as mass slider left it
+fn part_mass(shape: &ColliderShapeComponent, mass_props: &ColliderMassPropsComponent) -> (f32, Option<f32>) {
+	let volume	= ColliderMassProps::Density(1.0).mass_properties(&***shape).mass();
+	let mass	= mass_props.mass_properties(&***shape).mass();
+	match **mass_props {
+		ColliderMassProps::Density(density)	=> (density, None),
+		ColliderMassProps::MassProperties(_)=> (mass / volume, Some(mass)),
+	}
+}
+
+// Description of the vehicle as it is now: geometry and joints from config, everything tuned on spawned parts
+// and drivetrain from the vehicle itself, the rest from the description it was spawned with
+pub fn capture_vehicle_desc(
+	desc			: &VehicleDesc,
+	vehicle_cfg		: &VehicleConfig,
+	driving_cfg		: &DrivingConfig,
+	input_cfg		: &InputConfig,
+	(body, vehicle, drivetrain, engine, gearbox, differentials, brakes, driving_aids)
+					: (Entity, &Vehicle, &Drivetrain, &Engine, &Gearbox, &Differentials, &Brakes, &DrivingAids),
+	parts			: &Query<PartQuery>,
+) -> VehicleDesc {
+	let mut out = vehicle_cfg.apply_to_desc(desc);
+	driving_cfg.apply_to_desc(&mut out.joints);
+
+	out.drivetrain		= drivetrain.clone();
+	out.engine			= engine.clone();
+	out.gearbox			= gearbox.clone();
+	out.differentials	= differentials.axles.iter().map(|axle| axle.settings.clone()).collect();
+	out.brakes			= brakes.clone();
+	out.driving_aids	= driving_aids.clone();
+	out.input			= input_cfg.clone();
+
+	if let Ok((_, _, material, _)) = parts.get(body) {
+		out.body.material = MaterialDesc::from_collider(material);
+	}
+
+	for (wheel_desc, wheel) in out.wheels.iter_mut().zip(vehicle.wheels.iter()) {
+		wheel_desc.offset	= wheel.offset;
+
+		if let Ok((shape, mass_props, material, tire)) = parts.get(wheel.wheel) {
+			if let Some(cylinder) = shape.as_cylinder() {
+				wheel_desc.radius		= cylinder.radius;
+				wheel_desc.half_width	= cylinder.half_height;
+			}
+			let (density, mass)	= part_mass(shape, mass_props);
+			wheel_desc.density	= density;
+			wheel_desc.mass		= mass;
+			wheel_desc.material	= MaterialDesc::from_collider(material);
+			if let Some(tire) = tire {
+				wheel_desc.tire	= tire.desc.clone();
+			}
+		}
+	}
+
+	// axles that were all tuned the same way go to shared axle description, the rest are kept per wheel
+	let axles: Vec<(f32, Option<f32>, MaterialDesc)> = vehicle.wheels.iter()
+		.filter_map(|wheel| parts.get(wheel.axle).ok())
+		.map(|(shape, mass_props, material, _)| {
+			let (density, mass) = part_mass(shape, mass_props);
+			(density, mass, MaterialDesc::from_collider(material))
+		})
+		.collect();
+
+	if let Some(&(first_density, _, first_material)) = axles.first() {
+		if axles.len() == out.wheels.len() {
+			let same_density	= axles.iter().all(|(density, ..)| *density == first_density);
+			let same_material	= axles.iter().all(|(.., material)| *material == first_material);
+			if same_density {
+				out.axle.density	= first_density;
+			}
+			if same_material {
+				out.axle.material	= first_material;
+			}
+
+			for (wheel_desc, (density, mass, material)) in out.wheels.iter_mut().zip(axles) {
+				wheel_desc.axle_density	= if same_density { None } else { Some(density) };
+				wheel_desc.axle_mass	= mass;
+				wheel_desc.axle_material= if same_material { None } else { Some(material) };
+			}
+		}
+	}
+
+	out
+}
+
+// joints first, then everything they were holding together
+pub fn despawn_vehicle(body: Entity, vehicle: &Vehicle, commands: &mut Commands) {
+	for wheel in vehicle.wheels.iter() {
+		commands.entity(wheel.suspension_joint).despawn();
+		commands.entity(wheel.axle_joint).despawn();
+		commands.entity(wheel.wheel_joint).despawn();
+	}
+
+	for wheel in vehicle.wheels.iter() {
+		commands.entity(wheel.suspension).despawn();
+		commands.entity(wheel.axle).despawn();
+		commands.entity(wheel.wheel).despawn();
+	}
+
+	commands.entity(body).despawn();
+}
+
 fn main() {
-	App::new()
+	let args			= CliArgs::parse();
+	let vehicle_desc	= VehicleDesc::load_or_default(args.vehicle.as_deref());
+	let mut active_scene= ActiveScene::new(match args.scene.as_deref() {
+		Some(name)		=> Scene::from_name(name).unwrap_or_else(|| {
+			println!("unknown scene {:?}, expected one of {:?}", name, Scene::ALL.iter().map(|s| s.name()).collect::<Vec<_>>());
+			Scene::default()
+		}),
+		None			=> Scene::default(),
+	});
+	match args.terrain.as_deref().map(TerrainDesc::from_arg) {
+		Some(Ok(desc))	=> active_scene.terrain = desc.with_seed(args.seed),
+		Some(Err(err))	=> println!("{}. Using scene terrain", err),
+		None			=> (),
+	}
+	match args.course.as_deref().map(CourseDesc::load) {
+		Some(Ok(desc))	=> active_scene.course = desc,
+		Some(Err(err))	=> println!("{}. Using scene obstacles", err),
+		None			=> (),
+	}
+
+	let mut app			= App::new();
+	app
+		.init_resource::<Game>()
+		.init_resource::<SurfaceRegistry>()
+		.insert_resource(VehicleConfig::from_desc(&vehicle_desc))
+		.insert_resource(DrivingConfig::from_desc(&vehicle_desc.joints))
+		.insert_resource(SpawnPoint::from_desc(&vehicle_desc))
+		.insert_resource(vehicle_desc.input.clone())
+		.init_resource::<ResetRequest>()
+		.insert_resource(vehicle_desc)
+		.insert_resource(active_scene);
+
+	input::setup_input(&mut app, args.script.as_deref(), !args.headless);
+	add_vehicle_systems(&mut app);
+
+	if args.headless {
+		headless::setup_app(&mut app, args.steps);
+	} else {
+		setup_windowed_app(&mut app);
+	}
+
+	app.run();
+}
+
+// physics with contact hooks, shared by windowed and headless apps
+fn add_physics_plugin(app: &mut App) {
+	app
+		.insert_resource(PhysicsHooksWithQueryObject::<ContactQuery>(Box::new(tire::TireContactHook)))
+		.add_plugin(RapierPhysicsPlugin::<ContactQuery>::default());
+}
+
+// vehicle simulation shared by windowed and headless apps
+fn add_vehicle_systems(app: &mut App) {
+	app
+		.add_system(wheel_state_system.label("wheel_state").after("vehicle_input"))
+		.add_system(tire::tire_system.after("wheel_state"))
+		.add_system(engine::powertrain_system.label("powertrain").after("wheel_state"))
+		.add_system(differential::differential_system.label("differential").after("powertrain"))
+		.add_system(brakes::brake_system.label("brakes").after("differential"))
+		.add_system(driving_aids::driving_aids_system.label("driving_aids").after("brakes"))
+		.add_system(surface::rolling_resistance_system.label("rolling_resistance").after("driving_aids"))
+		.add_system(accelerate_system.label("wheel_motors").after("rolling_resistance"));
+}
+
+fn setup_windowed_app(app: &mut App) {
+	app
 		.insert_resource(ClearColor(Color::rgb(
 			0xF9 as f32 / 255.0,
 			0xF9 as f32 / 255.0,
 			0xFF as f32 / 255.0,
 		)))
 		.insert_resource(Msaa::default())
-		.init_resource::<Game>()
-		.insert_resource(VehicleConfig::default())
-		.add_plugins(DefaultPlugins)
-		.add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+		.add_plugins(DefaultPlugins);
+
+	add_physics_plugin(app);
+
+	app
 		.add_plugin(RapierRenderPlugin)
 		.add_plugin(FlyCameraPlugin)
 		.add_plugin(bevy_egui::EguiPlugin)
@@ -89,10 +462,17 @@ fn main() {
 		.add_system(cursor_grab_system)
 		.add_system(toggle_button_system)
 		.add_system(camera_collision_system)
-		.add_system(accelerate_system)
 		.add_system(update_ui)
-		.add_system_to_stage(CoreStage::PostUpdate, display_events_system)
-		.run();
+		.add_system(draw_weight_ui)
+		.add_system(scene::draw_scene_ui)
+		.add_system(scene::scene_respawn_system)
+		.add_system(reset::reset_keys_system.label("reset_keys"))
+		.add_system(reset::draw_reset_ui.label("reset_ui"))
+		.add_system(reset::reset_vehicle_system.after("reset_keys").after("reset_ui"))
+		.init_resource::<PresetLibrary>()
+		.add_system(presets::draw_presets_ui.label("presets_ui"))
+		.add_system(presets::preset_system.after("presets_ui"))
+		.add_system_to_stage(CoreStage::PostUpdate, display_events_system);
 }
 
 fn setup_grab_system(mut windows: ResMut<Windows>) {
@@ -169,18 +549,16 @@ fn spawn_camera(
 pub fn setup_physics_system(
 	mut configuration	: ResMut<RapierConfiguration>,
 	mut game			: ResMut<Game>,
-		vehicle_cfg		: Res<VehicleConfig>,
+		vehicle_desc	: Res<VehicleDesc>,
+		surfaces		: Res<SurfaceRegistry>,
+		active_scene	: Res<ActiveScene>,
+	mut meshes			: Option<ResMut<Assets<Mesh>>>,
+	mut materials		: Option<ResMut<Assets<StandardMaterial>>>,
 	mut commands		: Commands
 ) {
 //	configuration.timestep_mode = TimestepMode::VariableTimestep;
 
-	spawn_ground		(&mut game, &mut commands);
-
-	if true {
-		spawn_cubes		(&mut commands);
-	}
-
-	spawn_vehicle		(&mut game, &vehicle_cfg, &mut commands);
+	scene::spawn_scene	(&active_scene, &mut game, &vehicle_desc, &surfaces, meshes.as_deref_mut(), materials.as_deref_mut(), &mut commands);
 }
 
 fn setup_camera_system(
@@ -197,6 +575,7 @@ fn setup_camera_system(
 
 fn spawn_ground(
 	game				: &mut ResMut<Game>,
+	surfaces			: &SurfaceRegistry,
 	commands			: &mut Commands
 ) {
 	let ground_size 	= 200.1;
@@ -205,6 +584,7 @@ fn spawn_ground(
 	let ground_bundle 	= ColliderBundle {
 		shape			: ColliderShape::cuboid(ground_size, ground_height, ground_size).into(),
 		position		: Vec3::new(0.0, -ground_height, 0.0).into(),
+		material		: surfaces.collider_material(Surface::Asphalt).into(),
 		..ColliderBundle::default()
 	};
 
@@ -212,95 +592,194 @@ fn spawn_ground(
 		.spawn_bundle	(ground_bundle)
 		.insert			(ColliderDebugRender::default())
 		.insert			(ColliderPositionSync::Discrete)
+		.insert			(surfaces.get(Surface::Asphalt))
 		.id				();
 
 	println!			("ground Entity ID {:?}", ground);
 }
 
+// a row of patches in front of the vehicle to compare how it behaves on different surfaces
+fn spawn_surface_patches(
+	surfaces			: &SurfaceRegistry,
+	commands			: &mut Commands
+) {
+	let half_size		= Vec2::new(5.0, 10.0);
+	let kinds			= [Surface::Gravel, Surface::Mud, Surface::Ice, Surface::Grass];
+
+	for (i, kind) in kinds.iter().enumerate() {
+		let center		= Vec3::new(0.0, 0.0, 25.0 + i as f32 * half_size.y * 2.0);
+		let patch		= surface::spawn_surface_patch(*kind, center, half_size, surfaces, commands);
+		println!		("{:?} patch Entity ID {:?}", kind, patch);
+	}
+}
+
 fn spawn_vehicle(
 		game			: &mut ResMut<Game>,
-		vehicle_cfg		: &Res<VehicleConfig>,
+		desc			: &VehicleDesc,
 	mut commands		: &mut Commands
 ) {
-	let body_pos = Vec3::new(0.0, 5.5, 0.0);
-	let body_half_size = Vec3::new(0.5, 0.5, 1.0);
-	let body = spawn_body(body_pos, body_half_size, RigidBodyType::Dynamic, &mut commands);
+	let body_pos = desc.body.position;
+	let body = spawn_body(body_pos, desc.body.half_size, desc.body.mass_properties(), RigidBodyType::Dynamic, &mut commands);
 	game.body = Some(body);
 	println!("body Entity ID {:?}", body);
 
-	// offsets for wheel placement relative to body center
-	let x_off = 0.8;
-	let y_off = 0.8;
-	let z_off = 1.4;
+	commands.entity(body).insert(ColliderMaterialComponent::from(desc.body.material.collider_material()));
 
-	{
-		let offset = Vec3::new(x_off, -y_off, z_off);
-		let (rf_axle_joint, rf_wheel_joint, rf_wheel) =
-		spawn_attached_wheel("RF".to_string(), Tag::FrontWheel, body, body_pos, offset, vehicle_cfg.front_hh, vehicle_cfg.front_r, &mut commands);
-		(game.rf_axle_joint, game.rf_wheel_joint, game.rf_wheel) = (Some(rf_axle_joint), Some(rf_wheel_joint), Some(rf_wheel));
-		println!("rf_wheel Entity ID {:?}", rf_wheel);
-	}
+	let mut vehicle = Vehicle::default();
 
-	if true {
-		let offset = Vec3::new(-x_off, -y_off, z_off);
-		let (lf_axle_joint, lf_wheel_joint, lf_wheel) =
-		spawn_attached_wheel("LF".to_string(), Tag::FrontWheel, body, body_pos, offset, vehicle_cfg.front_hh, vehicle_cfg.front_r, &mut commands);
-		(game.lf_axle_joint, game.lf_wheel_joint, game.lf_wheel) = (Some(lf_axle_joint), Some(lf_wheel_joint), Some(lf_wheel));
-		println!("lf_wheel Entity ID {:?}", lf_wheel);
-	}
+	for wheel_desc in desc.wheels.iter() {
+		let wheel_record = spawn_attached_wheel(body, body_pos, wheel_desc, &desc.axle, &desc.joints, &mut commands);
+		println!("{} wheel Entity ID {:?}", wheel_desc.name, wheel_record.wheel);
 
-	if true {
-		let offset = Vec3::new(x_off, -y_off, -z_off);
-		let (rr_axle_joint, rr_wheel_joint, rr_wheel) =
-		spawn_attached_wheel("RR".to_string(), Tag::RearWheel, body, body_pos, offset, vehicle_cfg.rear_hh, vehicle_cfg.rear_r, &mut commands);
-		(game.rr_axle_joint, game.rr_wheel_joint, game.rr_wheel) = (Some(rr_axle_joint), Some(rr_wheel_joint), Some(rr_wheel));
-		println!("rr_wheel Entity ID {:?}", rr_wheel);
+		vehicle.wheels.push(wheel_record);
 	}
 
-	if true {
-		let offset = Vec3::new(-x_off, -y_off, -z_off);
-		let (lr_axle_joint, lr_wheel_joint, lr_wheel) =
-		spawn_attached_wheel("LR".to_string(), Tag::RearWheel, body, body_pos, offset, vehicle_cfg.rear_hh, vehicle_cfg.rear_r, &mut commands);
-		(game.lr_axle_joint, game.lr_wheel_joint, game.lr_wheel) = (Some(lr_axle_joint), Some(lr_wheel_joint), Some(lr_wheel));
-		println!("lr_wheel Entity ID {:?}", lr_wheel);
-	}
+	let differentials = Differentials::from_wheels(&vehicle.wheels, &desc.differential, &desc.differentials);
+
+	commands.entity(body)
+		.insert(vehicle)
+		.insert(differentials)
+		.insert(desc.brakes.clone())
+		.insert(desc.driving_aids.clone())
+		.insert(desc.drivetrain.clone())
+		.insert(desc.engine.clone())
+		.insert(desc.gearbox.clone());
 }
 
 fn spawn_attached_wheel(
-	prefix			: String,
-	tag				: Tag,
 	body			: Entity,
 	body_pos		: Vec3,
-	main_offset		: Vec3,
-	half_height		: f32,
-	radius			: f32, 
+	wheel_desc		: &WheelDesc,
+	axle_desc		: &AxleDesc,
+	joints_desc		: &JointsDesc,
 	mut	commands	: &mut Commands
-) -> (Entity, Entity, Entity) {
-	let x_sign		= main_offset.x * (1.0 / main_offset.x.abs());
-	let wheel_offset= Vec3::X * 0.8 * x_sign; // 0.2 offset by x axis
+) -> WheelRecord {
+	let prefix		= wheel_desc.name.clone();
+	let tag			= if wheel_desc.front { Tag::FrontWheel } else { Tag::RearWheel };
+
+	let main_offset	= wheel_desc.offset;
+	let wheel_offset= Vec3::X * wheel_anchor_x(main_offset.x, axle_desc.wheel_offset, axle_desc.half_size.x, wheel_desc.half_width);
 
-	let axle_size	= Vec3::new(0.1, 0.2, 0.1);
 	let axle_pos	= body_pos + main_offset;
-	let axle		= spawn_axle(&prefix, axle_pos, axle_size, RigidBodyType::Dynamic, &mut commands);
+	let axle_density= wheel_desc.axle_density.unwrap_or(axle_desc.density);
+	let suspension	= spawn_suspension(&prefix, axle_pos, axle_desc.half_size, axle_density, &mut commands);
 
-	let mut anchor1	= main_offset;
+	// suspension is mounted rest_length above the axle, so at rest the spring is neither compressed nor extended
+	let mut anchor1	= main_offset + Vec3::Y * joints_desc.suspension_rest_length;
 	let mut anchor2 = Vec3::ZERO;
-	let axle_joint 	= spawn_axle_joint(body, axle, point![anchor1.x, anchor1.y, anchor1.z], point![anchor2.x, anchor2.y, anchor2.z], &mut commands);
+	let suspension_joint = spawn_suspension_joint(body, suspension, point![anchor1.x, anchor1.y, anchor1.z], point![anchor2.x, anchor2.y, anchor2.z], joints_desc, &mut commands);
+
+	let axle		= spawn_axle(&prefix, axle_pos, axle_desc.half_size, axle_density, wheel_desc.axle_mass, RigidBodyType::Dynamic, &mut commands);
+	commands.entity(axle).insert(ColliderMaterialComponent::from(wheel_desc.axle_material.unwrap_or(axle_desc.material).collider_material()));
+
+	anchor1			= Vec3::ZERO;
+	anchor2			= Vec3::ZERO;
+	let axle_joint 	= spawn_axle_joint(suspension, axle, point![anchor1.x, anchor1.y, anchor1.z], point![anchor2.x, anchor2.y, anchor2.z], joints_desc, &mut commands);
 
 	let wheel_pos 	= axle_pos + wheel_offset;
-	let wheel 		= spawn_wheel(&prefix, tag, wheel_pos, half_height, radius, RigidBodyType::Dynamic, &mut commands);
+	let wheel 		= spawn_wheel(&prefix, tag, wheel_pos, wheel_desc.half_width, wheel_desc.radius, wheel_desc.density, wheel_desc.mass, RigidBodyType::Dynamic, &mut commands);
+	commands.entity(wheel)
+		.insert(WheelState::default())
+		.insert(Tire::new(&wheel_desc.tire))
+		.insert(ColliderMaterialComponent::from(wheel_desc.material.collider_material()));
 
 	anchor1			= wheel_offset;
 	anchor2 		= Vec3::ZERO;
 	let wheel_joint = spawn_wheel_joint(axle, wheel, point![anchor1.x, anchor1.y, anchor1.z], point![anchor2.x, anchor2.y, anchor2.z], &mut commands);
 
-	(axle_joint, wheel_joint, wheel)
+	WheelRecord {
+		name			: prefix,
+		suspension,
+		suspension_joint,
+		axle,
+		axle_joint,
+		wheel_joint,
+		wheel,
+		offset			: main_offset,
+		front			: wheel_desc.front,
+		steered			: wheel_desc.steered,
+		driven			: wheel_desc.driven,
+	}
+}
+
+// wheel anchor on the axle, mirrored for the left side. Wheel is pushed out when it would overlap the axle
+fn wheel_anchor_x(offset_x: f32, wheel_offset: f32, axle_half_x: f32, wheel_half_width: f32) -> f32 {
+	let x_sign		= if offset_x < 0.0 { -1.0 } else { 1.0 };
+	wheel_offset.max(axle_half_x + wheel_half_width) * x_sign
+}
+
+// suspension is a body between vehicle body and axle that only links joints, so it has no collider to bump into things
+// and gets its mass set explicitly instead, because joints are only stable when this body has mass in the same range as the axle
+fn spawn_suspension(
+	prefix			: &String,
+	pos_in			: Vec3,
+	half_size		: Vec3,
+	density			: f32,
+	commands		: &mut Commands,
+) -> Entity {
+	let mut pos_comp = RigidBodyPositionComponent::default();
+	pos_comp.position.translation = pos_in.clone().into();
+
+	let mass_props = RigidBodyMassProps {
+		local_mprops: MassProperties::from_cuboid(density, vector![half_size.x, half_size.y, half_size.z]),
+		..Default::default()
+	};
+
+	let rigid_body = RigidBodyBundle {
+		position: pos_comp,
+		mass_properties: mass_props.into(),
+		..RigidBodyBundle::default()
+	};
+
+	commands
+		.spawn()
+		.insert_bundle(rigid_body)
+		.insert(NameComponent{ name: format!("{} Suspension", prefix) })
+		.id()
+}
+
+fn spawn_suspension_joint(
+	entity1: Entity,
+	entity2: Entity,
+	anchor1: nalgebra::Point3<Real>,
+	anchor2: nalgebra::Point3<Real>,
+	joints_desc: &JointsDesc,
+	commands: &mut Commands,
+) -> Entity {
+	let rest_length	= joints_desc.suspension_rest_length;
+	let travel		= joints_desc.suspension_travel;
+
+	// pointing down so that joint position is the distance between mount point and axle
+	let suspension_joint = PrismaticJoint::new(-Vector::y_axis())
+		.local_anchor1(anchor1)
+		.local_anchor2(anchor2)
+		.limit_axis([rest_length - travel, rest_length + travel])
+		.motor_position(rest_length, joints_desc.suspension_stiffness, joints_desc.suspension_damping);
+
+	commands
+		.spawn()
+		.insert(JointBuilderComponent::new(suspension_joint, entity1, entity2))
+		.insert(NameComponent{ name: "Suspension".to_string() })
+		.id()
+}
+
+// explicit mass becomes mass properties of the shape, same as mass slider in Parameters window does it
+fn part_mass_props(shape: &ColliderShape, density: f32, mass: Option<f32>) -> ColliderMassProps {
+	match mass {
+		Some(mass) => {
+			let volume = ColliderMassProps::Density(1.0).mass_properties(&**shape).mass();
+			ColliderMassProps::MassProperties(Box::new(ColliderMassProps::Density(mass / volume).mass_properties(&**shape)))
+		},
+		None => ColliderMassProps::Density(density),
+	}
 }
 
 fn spawn_axle(
 	prefix			: &String,
 	pos_in			: Vec3,
 	half_size		: Vec3,
+	density			: f32,
+	mass			: Option<f32>,
 	body_type		: RigidBodyType,
 	commands		: &mut Commands,
 ) -> Entity {
@@ -318,10 +797,11 @@ fn spawn_axle(
 		..Default::default()
 	};
 
+	let shape = ColliderShape::cuboid(half_size.x, half_size.y, half_size.z);
 	let axle_collider = ColliderBundle {
-		shape: ColliderShape::cuboid(half_size.x, half_size.y, half_size.z).into(),
+		mass_properties: part_mass_props(&shape, density, mass).into(),
+		shape: shape.into(),
 		position: translated_position.into(),
-		mass_properties: ColliderMassProps::Density(1000.0).into(),
 		..ColliderBundle::default()
 	};
 
@@ -342,6 +822,8 @@ fn spawn_wheel(
 	pos_in: Vec3,
 	half_height: f32,
 	radius: f32,
+	density: f32,
+	mass: Option<f32>,
 	body_type: RigidBodyType,
 	commands: &mut Commands,
 ) -> Entity {
@@ -365,11 +847,17 @@ fn spawn_wheel(
 		..Default::default()
 	};
 
+	let shape = ColliderShape::cylinder(half_height, radius);
 	let wheel_collider = ColliderBundle {
-		shape: ColliderShape::cylinder(half_height, radius).into(),
+		mass_properties: part_mass_props(&shape, density, mass).into(),
+		shape: shape.into(),
 		position: rotated_position.into(),
-		mass_properties: ColliderMassProps::Density(2.0).into(),
-		flags: (ActiveEvents::INTERSECTION_EVENTS | ActiveEvents::CONTACT_EVENTS).into(),
+		flags: ColliderFlags {
+			active_events: ActiveEvents::INTERSECTION_EVENTS | ActiveEvents::CONTACT_EVENTS,
+			// tire model sets friction of wheel contacts
+			active_hooks: ActiveHooks::MODIFY_SOLVER_CONTACTS,
+			..Default::default()
+		}.into(),
 		..ColliderBundle::default()
 	};
 
@@ -389,12 +877,13 @@ fn spawn_axle_joint(
 	entity2: Entity,
 	anchor1: nalgebra::Point3<Real>,
 	anchor2: nalgebra::Point3<Real>,
+	joints_desc: &JointsDesc,
 	commands: &mut Commands,
 ) -> Entity {
 	let axle_joint = RevoluteJoint::new(Vector::y_axis())
 		.local_anchor1(anchor1)
 		.local_anchor2(anchor2)
-		.motor_position(0.0, 10.0, 3.0); // by default we want axle joint to stay fixed 
+		.motor_position(0.0, joints_desc.axle_stiffness, joints_desc.axle_damping); // by default we want axle joint to stay fixed 
 
 	commands
 		.spawn()
@@ -423,6 +912,7 @@ fn spawn_wheel_joint(
 fn spawn_body(
 	pos_in: Vec3,
 	half_size: Vec3,
+	mass_props: MassProperties,
 	body_type: RigidBodyType,
 	commands: &mut Commands,
 ) -> Entity {
@@ -437,7 +927,8 @@ fn spawn_body(
 
 	let box_collider = ColliderBundle {
 		shape: ColliderShape::cuboid(half_size.x, half_size.y, half_size.z).into(),
-		mass_properties: ColliderMassProps::Density(10.0).into(), // joints like it when there is an hierarchy of masses and we want body to be the heaviest
+		// explicit so that center of mass and inertia can be set
+		mass_properties: ColliderMassProps::MassProperties(Box::new(mass_props)).into(),
 		..ColliderBundle::default()
 	};
 
@@ -567,17 +1058,20 @@ fn toggle_button_system(
 	}
 }
 
-fn motor_velocity(velocity: f32, factor: f32, joint_e: Entity, joints: &mut ResMut<ImpulseJointSet>, query: &mut Query<&mut JointHandleComponent>) {
-	let 	joint_comp	= query.get(joint_e).unwrap();
-	let mut joint		= joints.get_mut(joint_comp.handle()).unwrap();
-			joint.data	= joint.data.motor_velocity(JointAxis::AngX, velocity, factor);
+// Joints of a vehicle spawned this frame only have JointBuilderComponent until rapier creates them, those are skipped
+fn motor_velocity(velocity: f32, factor: f32, max_torque: f32, joint_e: Entity, joints: &mut ResMut<ImpulseJointSet>, query: &mut Query<&mut JointHandleComponent>) {
+	let 	joint_comp	= match query.get(joint_e) { Ok(j) => j, Err(_) => return };
+	let mut joint		= match joints.get_mut(joint_comp.handle()) { Some(j) => j, None => return };
+			joint.data	= joint.data
+				.motor_velocity(JointAxis::AngX, velocity, factor)
+				.motor_max_force(JointAxis::AngX, max_torque);
 }
 
 fn motor_steer(angle: f32, stiffness: f32, damping: f32, joint_e: Entity, joints: &mut ResMut<ImpulseJointSet>, query: &mut Query<&mut JointHandleComponent>) {
-	let 	joint_comp 	= query.get(joint_e).unwrap();
+	let 	joint_comp 	= match query.get(joint_e) { Ok(j) => j, Err(_) => return };
 
 	let		angle_rad	= angle.to_radians();
-	let mut joint 		= joints.get_mut(joint_comp.handle()).unwrap();
+	let mut joint 		= match joints.get_mut(joint_comp.handle()) { Some(j) => j, None => return };
 			joint.data 	= joint.data.motor_position(JointAxis::AngX, angle_rad, stiffness, damping)
 
 //	println!("motor steer {} limit axes {:?}", angle, joint.data.limit_axes);
@@ -588,55 +1082,104 @@ fn motor_steer(angle: f32, stiffness: f32, damping: f32, joint_e: Entity, joints
 //	}
 }
 
+fn motor_suspension(rest_length: f32, travel: f32, stiffness: f32, damping: f32, joint_e: Entity, joints: &mut ResMut<ImpulseJointSet>, query: &mut Query<&mut JointHandleComponent>) {
+	let 	joint_comp	= match query.get(joint_e) { Ok(j) => j, Err(_) => return };
+	let mut joint		= match joints.get_mut(joint_comp.handle()) { Some(j) => j, None => return };
+			joint.data	= joint.data
+				.limit_axis(JointAxis::X, [rest_length - travel, rest_length + travel])
+				.motor_position(JointAxis::X, rest_length, stiffness, damping);
+}
+
+fn set_anchor1(anchor: Vec3, joint_e: Entity, joints: &mut ResMut<ImpulseJointSet>, query: &mut Query<&mut JointHandleComponent>) {
+	let 	joint_comp	= match query.get(joint_e) { Ok(j) => j, Err(_) => return };
+	let mut joint		= match joints.get_mut(joint_comp.handle()) { Some(j) => j, None => return };
+			joint.data.set_local_anchor1(point![anchor.x, anchor.y, anchor.z]);
+}
+
+// Mount points on the body follow wheel offsets and suspension rest length, wheel anchors on axles follow axle
+// and wheel sizes. Joints then pull parts to their new places
+fn update_wheel_anchors(vehicle: &Vehicle, cfg: &VehicleConfig, joints: &mut ResMut<ImpulseJointSet>, query: &mut Query<&mut JointHandleComponent>) {
+	for wheel in vehicle.wheels.iter() {
+		let mount		= wheel.offset + Vec3::Y * cfg.susp_rest_length;
+		set_anchor1(mount, wheel.suspension_joint, joints, query);
+
+		let half_width	= if wheel.front { cfg.front_hh } else { cfg.rear_hh };
+		let wheel_x		= wheel_anchor_x(wheel.offset.x, cfg.wheel_offset, cfg.axle_half_size.x, half_width);
+		set_anchor1(Vec3::X * wheel_x, wheel.wheel_joint, joints, query);
+	}
+}
+
+// below this speed slip ratio is computed as if ground speed was this, otherwise it explodes at standstill
+const SLIP_MIN_SPEED : f32 = 1.0;
+
+// Wheel spin relative to axle around axle's x axis, same thing wheel joint motor works with.
+// Slip ratio compares wheel surface speed with chassis velocity at the wheel's lowest point
+fn wheel_state_system(
+		vehicles	: Query<(Entity, &Vehicle)>,
+		bodies		: Query<(&RigidBodyVelocityComponent, &RigidBodyPositionComponent, &RigidBodyMassPropsComponent)>,
+		shapes		: Query<&ColliderShapeComponent>,
+	mut wheel_states: Query<&mut WheelState>,
+) {
+	for (body, vehicle) in vehicles.iter() {
+		let (body_vel, _, body_mprops) = match bodies.get(body) { Ok(b) => b, Err(_) => continue };
+
+		for wheel in vehicle.wheels.iter() {
+			let (axle_vel, axle_pos, _)		= match bodies.get(wheel.axle) { Ok(b) => b, Err(_) => continue };
+			let (wheel_vel, wheel_pos, _)	= match bodies.get(wheel.wheel) { Ok(b) => b, Err(_) => continue };
+
+			let radius = shapes.get(wheel.wheel).ok()
+				.and_then(|shape| shape.as_cylinder().map(|c| c.radius))
+				.unwrap_or(0.0);
+
+			let axis		= axle_pos.position.rotation * Vector::x();
+			let forward		= axle_pos.position.rotation * Vector::z();
+			let lateral		= axis;
+
+			let contact		= wheel_pos.position.translation.vector - Vector::y() * radius;
+			let arm			= contact - body_mprops.world_com.coords;
+			let contact_vel	= body_vel.linvel + body_vel.angvel.cross(&arm);
+
+			if let Ok(mut state) = wheel_states.get_mut(wheel.wheel) {
+				state.spin			= (wheel_vel.angvel - axle_vel.angvel).dot(&axis);
+				state.ground_speed	= contact_vel.dot(&forward);
+				state.slip_ratio	= (state.spin * radius - state.ground_speed) / state.ground_speed.abs().max(SLIP_MIN_SPEED);
+				state.slip_angle	= contact_vel.dot(&lateral).atan2(state.ground_speed.abs().max(SLIP_MIN_SPEED));
+			}
+		}
+	}
+}
+
 fn accelerate_system(
-		key		: Res<Input<KeyCode>>,
-		game	: ResMut<Game>,
+		input	: Res<VehicleInput>,
+		j		: Res<DrivingConfig>,
+		cfg		: Res<VehicleConfig>,
 	mut	joints	: ResMut<ImpulseJointSet>,
+		vehicles: Query<&Vehicle>,
+		wheel_states : Query<&WheelState>,
 	mut	query	: Query<&mut JointHandleComponent>,
 ) {
-	let rf_axle_joint = game.rf_axle_joint.unwrap();
-	let lf_axle_joint = game.lf_axle_joint.unwrap();
-	let rr_axle_joint = game.rr_axle_joint.unwrap();
-	let lr_axle_joint = game.lr_axle_joint.unwrap();
-
-	let rf_wheel_joint = game.rf_wheel_joint.unwrap();
-	let lf_wheel_joint = game.lf_wheel_joint.unwrap();
-	let rr_wheel_joint = game.rr_wheel_joint.unwrap();
-	let lr_wheel_joint = game.lr_wheel_joint.unwrap();
-
-	if key.just_pressed(KeyCode::W) {
-		motor_velocity(10.0, 0.7, rr_wheel_joint, &mut joints, &mut query);
-		motor_velocity(10.0, 0.7, lr_wheel_joint, &mut joints, &mut query);
-	} else if key.just_released(KeyCode::W) {
-		motor_velocity(0.0, 0.7, rr_wheel_joint, &mut joints, &mut query);
-		motor_velocity(0.0, 0.7, lr_wheel_joint, &mut joints, &mut query);
-	}
-	
-	 if key.just_pressed(KeyCode::S) {
-		motor_velocity(-10.0, 0.3, rr_wheel_joint, &mut joints, &mut query);
-		motor_velocity(-10.0, 0.3, lr_wheel_joint, &mut joints, &mut query);
-	} else if key.just_released(KeyCode::S) {
-		motor_velocity(0.0, 0.7, rr_wheel_joint, &mut joints, &mut query);
-		motor_velocity(0.0, 0.7, lr_wheel_joint, &mut joints, &mut query);
-	}
- 
-	let steer_angle = 20.0;
-	let stiffness = 5.0;
-	let damping = 3.0;
-	if key.just_pressed(KeyCode::D) {
-		motor_steer(-steer_angle, stiffness, damping, rf_axle_joint, &mut joints, &mut query);
-		motor_steer(-steer_angle, stiffness, damping, lf_axle_joint, &mut joints, &mut query);
-	} else if key.just_released(KeyCode::D) {
-		motor_steer(0.0, stiffness, damping, rf_axle_joint, &mut joints, &mut query);
-		motor_steer(0.0, stiffness, damping, lf_axle_joint, &mut joints, &mut query);
-	}
+	let steer_angle = input.steer * j.steer_angle;
+
+	for vehicle in vehicles.iter() {
+		let offsets: Vec<Vec3> = vehicle.wheels.iter().map(|w| w.offset).collect();
+		let steered: Vec<bool> = vehicle.wheels.iter().map(|w| w.steered).collect();
+		let angles = steering::ackermann_angles(steer_angle, cfg.ackermann_percent / 100.0, &offsets, &steered);
+
+		for (wheel, angle) in vehicle.wheels.iter().zip(angles) {
+			// wheels without drive torque get a motor with 0 torque which lets them roll freely.
+			// Joint has one motor, so brakes and drive fight over it: whichever torque is larger wins by the difference
+			if let Ok(state) = wheel_states.get(wheel.wheel) {
+				if state.brake_torque > state.drive_torque {
+					motor_velocity(0.0, j.drive_factor, state.brake_torque - state.drive_torque, wheel.wheel_joint, &mut joints, &mut query);
+				} else {
+					motor_velocity(state.drive_target, j.drive_factor, state.drive_torque - state.brake_torque, wheel.wheel_joint, &mut joints, &mut query);
+				}
+			}
 
- 	if key.just_pressed(KeyCode::A) {
-		motor_steer(steer_angle, stiffness, damping, rf_axle_joint, &mut joints, &mut query);
-		motor_steer(steer_angle, stiffness, damping, lf_axle_joint, &mut joints, &mut query);
-	} else if key.just_released(KeyCode::A) {
-		motor_steer(0.0, stiffness, damping, rf_axle_joint, &mut joints, &mut query);
-		motor_steer(0.0, stiffness, damping, lf_axle_joint, &mut joints, &mut query);
+			if wheel.steered {
+				motor_steer(angle, j.steer_stiffness, j.steer_damping, wheel.axle_joint, &mut joints, &mut query);
+			}
+		}
 	}
 }
 
@@ -691,6 +1234,27 @@ fn set_cylinder_r(
 	cylinder.radius = new_r;
 }
 
+fn set_cuboid_half_extents(
+	coll_shape: &mut ColliderShapeComponent,
+	half_extents: Vec3,
+) {
+	let 	shape 	= coll_shape.make_mut();
+	let 	cuboid	= shape.as_cuboid_mut().unwrap();
+	cuboid.half_extents = half_extents.into();
+}
+
+// Collider with density changes mass along with its shape, rigid body mass has to follow.
+// Explicit mass properties stay the same, so nothing changes for them
+fn update_resized_mass_props(
+	prev_props: MassProperties,
+	mass_props_coll: &ColliderMassPropsComponent,
+	mass_props_rbody: &mut Mut<RigidBodyMassPropsComponent>,
+	coll_shape: &ColliderShapeComponent,
+) {
+	mass_props_rbody.local_mprops -= prev_props;
+	mass_props_rbody.local_mprops += mass_props_coll.mass_properties(&***coll_shape);
+}
+
 fn draw_density_param_ui(
 	ui: &mut Ui,
 	name: &String,
@@ -699,6 +1263,9 @@ fn draw_density_param_ui(
 	coll_shape: &Mut<ColliderShapeComponent>,
 ) {
 	let prev_props = mass_props_coll.mass_properties(&****coll_shape).clone();
+	// mass of the shape with density 1 is its volume
+	let volume = ColliderMassProps::Density(1.0).mass_properties(&****coll_shape).mass();
+
 	match &mut mass_props_coll as &mut ColliderMassProps {
 		ColliderMassProps::Density(density) => {
 			if ui.add(
@@ -708,8 +1275,98 @@ fn draw_density_param_ui(
 				mass_props_rbody.local_mprops += mass_props_coll.mass_properties(&****coll_shape);
 			}; 
 		},
-		ColliderMassProps::MassProperties(_) => (),
+		ColliderMassProps::MassProperties(_) => {
+			if ui.button(format!("{} back to density", name)).clicked() {
+				**mass_props_coll = ColliderMassProps::Density(prev_props.mass() / volume).into();
+			}
+		},
 	};
+
+	// editing mass switches collider to explicit mass properties of the same shape
+	let mut mass = prev_props.mass();
+	if ui.add(
+		Slider::new(&mut mass, 0.01 ..= 5000.0).logarithmic(true).text(format!("{} Mass, kg", name))
+	).changed() {
+		let new_props = ColliderMassProps::Density(mass / volume).mass_properties(&****coll_shape);
+		**mass_props_coll = ColliderMassProps::MassProperties(Box::new(new_props)).into();
+		mass_props_rbody.local_mprops -= prev_props;
+		mass_props_rbody.local_mprops += new_props;
+	}
+}
+
+// total mass of all parts and how much of it every axle carries standing on level ground
+fn draw_weight_ui(
+	mut ui_context	: ResMut<EguiContext>,
+		vehicles	: Query<(Entity, &Vehicle)>,
+		bodies		: Query<(&RigidBodyPositionComponent, &RigidBodyMassPropsComponent)>,
+) {
+	egui::Window::new("Weight").show(ui_context.ctx_mut(), |ui| {
+		for (body, vehicle) in vehicles.iter() {
+			let body_pos = match bodies.get(body) { Ok((pos, _)) => pos.position, Err(_) => continue };
+
+			// center of mass of the whole vehicle in body space
+			let mut total_mass	= 0.0;
+			let mut com_z		= 0.0;
+			let parts			= vehicle.wheels.iter().flat_map(|w| [w.suspension, w.axle, w.wheel]);
+			for (_, mprops) in std::iter::once(body).chain(parts).filter_map(|entity| bodies.get(entity).ok()) {
+				let mass		= mprops.local_mprops.mass();
+				total_mass		+= mass;
+				com_z			+= body_pos.inverse_transform_point(&mprops.world_com).z * mass;
+			}
+			if total_mass > 0.0 {
+				com_z			/= total_mass;
+			}
+
+			ui.label(format!("Vehicle mass: {:.1} kg", total_mass));
+
+			let axles		= differential::group_axles(&vehicle.wheels);
+			let axle_z: Vec<f32>	= axles.iter().map(|axle| vehicle.wheels[axle[0]].offset.z).collect();
+			let wheel_counts: Vec<f32> = axles.iter().map(|axle| axle.len() as f32).collect();
+
+			for (axle, share) in axles.iter().zip(static_axle_shares(&axle_z, &wheel_counts, com_z)) {
+				let name = differential::axle_name(&vehicle.wheels, axle);
+				ui.label(format!("{}: {:.0} kg ({:.1}%)", name, total_mass * share, share * 100.0));
+			}
+		}
+	});
+}
+
+// Fraction of vehicle weight on every axle given their positions along z and center of mass position.
+// Every wheel has the same suspension stiffness, so standing still the body settles in a plane and wheel
+// load is linear along z: F = a + b * z per wheel, with loads adding up to the weight and their moment to that
+// of the center of mass. Axles that would have to pull the body down are lifted off and the rest solved again
+fn static_axle_shares(axle_z: &[f32], wheel_counts: &[f32], com_z: f32) -> Vec<f32> {
+	let mut active: Vec<usize> = (0..axle_z.len()).collect();
+
+	loop {
+		let mut shares	= vec![0.0; axle_z.len()];
+		if active.is_empty() {
+			return shares;
+		}
+
+		let k: f32		= active.iter().map(|&i| wheel_counts[i]).sum();
+		let s: f32		= active.iter().map(|&i| wheel_counts[i] * axle_z[i]).sum();
+		let q: f32		= active.iter().map(|&i| wheel_counts[i] * axle_z[i] * axle_z[i]).sum();
+		let det			= k * q - s * s;
+
+		// all axles in one place carry weight evenly per wheel
+		let (a, b)		= if det.abs() < 1e-6 { (1.0 / k, 0.0) } else { ((q - s * com_z) / det, (k * com_z - s) / det) };
+
+		for &i in active.iter() {
+			shares[i]	= wheel_counts[i] * (a + b * axle_z[i]);
+		}
+
+		let lifted		= active.iter().copied().filter(|&i| shares[i] < 0.0).min_by(|&x, &y| shares[x].total_cmp(&shares[y]));
+		match lifted {
+			Some(lifted) => active.retain(|&i| i != lifted),
+			None => return shares,
+		}
+	}
+}
+
+// "RF Wheel" -> "RF"
+fn wheel_section_name(name: &String) -> &str {
+	name.rsplit_once(' ').map_or(name.as_str(), |(section, _)| section)
 }
 
 fn draw_single_wheel_params_ui(
@@ -720,7 +1377,8 @@ fn draw_single_wheel_params_ui(
 	coll_shape: &mut Mut<ColliderShapeComponent>,
 	tag: &Tag,
 ) {
-	draw_density_param_ui(ui, &name[3..].to_string(), mass_props_coll, mass_props_rbody, coll_shape);
+	let part_name = name.rsplit_once(' ').map_or(name.as_str(), |(_, part)| part).to_string();
+	draw_density_param_ui(ui, &part_name, mass_props_coll, mass_props_rbody, coll_shape);
 
 	match tag {
 		Tag::FrontWheel | Tag::RearWheel => {
@@ -752,9 +1410,302 @@ fn draw_single_wheel_params_ui(
 	}
 }
 
+// what group edits changed that joints or config have to follow
+#[derive(Default)]
+struct GroupChanges {
+	front_wheels	: bool,
+	rear_wheels		: bool,
+	axles			: bool,
+	body_resized	: Option<(Vec3, Vec3)>,
+}
+
+// Slider showing value of the first entity in a group. Marked with * when other entities have different values,
+// moving it sets all of them
+fn group_slider(ui: &mut Ui, label: &str, values: &[f32], range: RangeInclusive<f32>) -> Option<f32> {
+	let mut value	= *values.first()?;
+	let diverged	= values.iter().any(|v| (v - value).abs() > 1e-4);
+	let text		= if diverged { format!("{} *", label) } else { label.to_string() };
+
+	let response	= ui.add(Slider::new(&mut value, range).text(text));
+	let response	= if diverged { response.on_hover_text("entities in this group have different values") } else { response };
+
+	if response.changed() { Some(value) } else { None }
+}
+
+fn shape_dimensions(shape: &ColliderShapeComponent) -> Vec<f32> {
+	if let Some(cylinder) = shape.as_cylinder() {
+		vec![cylinder.radius, cylinder.half_height]
+	} else if let Some(cuboid) = shape.as_cuboid() {
+		vec![cuboid.half_extents.x, cuboid.half_extents.y, cuboid.half_extents.z]
+	} else {
+		Vec::new()
+	}
+}
+
+fn set_shape_dimension(shape: &mut ColliderShapeComponent, index: usize, value: f32) {
+	let shape = shape.make_mut();
+	if let Some(cylinder) = shape.as_cylinder_mut() {
+		match index {
+			0 => cylinder.radius = value,
+			_ => cylinder.half_height = value,
+		}
+	} else if let Some(cuboid) = shape.as_cuboid_mut() {
+		cuboid.half_extents[index] = value;
+	}
+}
+
+// Density of explicit mass properties is kept as mass / volume with center of mass and inertia frame untouched
+fn set_density(
+	density: f32,
+	mass_props_coll: &mut Mut<ColliderMassPropsComponent>,
+	mass_props_rbody: &mut Mut<RigidBodyMassPropsComponent>,
+	coll_shape: &Mut<ColliderShapeComponent>,
+) {
+	let prev_props = mass_props_coll.mass_properties(&****coll_shape).clone();
+	let volume = ColliderMassProps::Density(1.0).mass_properties(&****coll_shape).mass();
+
+	let new_props = match &**mass_props_coll as &ColliderMassProps {
+		ColliderMassProps::Density(_) => ColliderMassProps::Density(density),
+		ColliderMassProps::MassProperties(props) => {
+			let mass = density * volume;
+			ColliderMassProps::MassProperties(Box::new(MassProperties::with_principal_inertia_frame(
+				props.local_com,
+				mass,
+				props.principal_inertia() * (mass / props.mass()),
+				props.principal_inertia_local_frame,
+			)))
+		},
+	};
+
+	**mass_props_coll = new_props.into();
+	mass_props_rbody.local_mprops -= prev_props;
+	mass_props_rbody.local_mprops += mass_props_coll.mass_properties(&****coll_shape);
+}
+
+// One set of sliders per Tag applied to every entity with that tag. Body goes through VehicleConfig
+// so that its explicit mass properties stay consistent with the Body section. Wheel contacts get friction
+// from the tire model, so for wheels friction slider is the tire peak friction
+fn draw_group_params_ui(
+	ui: &mut Ui,
+	cfg: &mut VehicleConfig,
+	query: &mut Query<(
+		&mut ColliderMassPropsComponent,
+		&mut RigidBodyMassPropsComponent,
+		&mut ColliderShapeComponent,
+		&mut ColliderMaterialComponent,
+		&NameComponent,
+		&Tag
+	)>,
+	tires: &mut Query<(&NameComponent, &mut Tire, &WheelState, &Tag)>,
+) -> GroupChanges {
+	let mut changes = GroupChanges::default();
+
+	let groups = [
+		(Tag::FrontWheel,	"Front wheels",	["Radius", "Half height", ""],					0.05 ..= 1.0),
+		(Tag::RearWheel,	"Rear wheels",	["Radius", "Half height", ""],					0.05 ..= 1.0),
+		(Tag::Axle,			"Axles",		["Half size X", "Half size Y", "Half size Z"],	0.05 ..= 1.0),
+		(Tag::Body,			"Body",			["Half size X", "Half size Y", "Half size Z"],	0.05 ..= 5.0),
+	];
+
+	ui.collapsing("Groups".to_string(), |ui| {
+	for (group, group_name, dimension_names, dimension_range) in groups {
+		ui.collapsing(group_name.to_string(), |ui| {
+		ui.vertical(|ui| {
+
+		let mut densities	= Vec::new();
+		let mut dimensions	= Vec::new();
+		let mut frictions	= Vec::new();
+		let mut restitutions= Vec::new();
+
+		for (mass_props_coll, _, coll_shape, material, _, tag) in query.iter() {
+			if *tag != group {
+				continue;
+			}
+
+			let volume = ColliderMassProps::Density(1.0).mass_properties(&***coll_shape).mass();
+			densities.push(mass_props_coll.mass_properties(&***coll_shape).mass() / volume);
+			dimensions.push(shape_dimensions(coll_shape));
+			frictions.push(material.friction);
+			restitutions.push(material.restitution);
+		}
+
+		let tire_group = matches!(group, Tag::FrontWheel | Tag::RearWheel);
+		if tire_group {
+			frictions = tires.iter().filter(|(_, _, _, tag)| **tag == group).map(|(_, tire, _, _)| tire.desc.peak_friction).collect();
+		}
+
+		if densities.is_empty() {
+			ui.label("nothing spawned");
+			return;
+		}
+
+		let new_density		= group_slider(ui, "Density", &densities, 0.01 ..= 1000.0);
+
+		let mut new_dimension = None;
+		for (index, dimension_name) in dimension_names.iter().enumerate() {
+			let values: Vec<f32> = dimensions.iter().filter_map(|d| d.get(index).copied()).collect();
+			if let Some(value) = group_slider(ui, dimension_name, &values, dimension_range.clone()) {
+				new_dimension = Some((index, value));
+			}
+		}
+
+		let new_friction	= if tire_group {
+			group_slider(ui, "Peak friction", &frictions, 0.1 ..= 3.0)
+		} else {
+			group_slider(ui, "Friction", &frictions, 0.0 ..= 2.0)
+		};
+		let new_restitution	= group_slider(ui, "Restitution", &restitutions, 0.0 ..= 1.0);
+
+		if new_density.is_none() && new_dimension.is_none() && new_friction.is_none() && new_restitution.is_none() {
+			return;
+		}
+
+		// keep config in sync so that joints, resets and the other sections see the same values
+		if let Some((index, value)) = new_dimension {
+			match group {
+				Tag::FrontWheel => {
+					if index == 0 { cfg.front_r = value } else { cfg.front_hh = value }
+					changes.front_wheels = true;
+				},
+				Tag::RearWheel => {
+					if index == 0 { cfg.rear_r = value } else { cfg.rear_hh = value }
+					changes.rear_wheels = true;
+				},
+				Tag::Axle => {
+					cfg.axle_half_size[index] = value;
+					changes.axles = true;
+				},
+				Tag::Body => {
+					let old = cfg.body_half_size;
+					cfg.body_half_size[index] = value;
+					changes.body_resized = Some((old, cfg.body_half_size));
+				},
+			}
+		}
+		if let (Tag::Body, Some(density)) = (group, new_density) {
+			cfg.body_density = density;
+		}
+
+		for (mut mass_props_coll, mut mass_props_rbody, mut coll_shape, mut material, _, tag) in query.iter_mut() {
+			if *tag != group {
+				continue;
+			}
+
+			if let Some((index, value)) = new_dimension {
+				let prev_props = mass_props_coll.mass_properties(&***coll_shape);
+				set_shape_dimension(&mut coll_shape, index, value);
+				// body mass props are rebuilt from config below
+				if group != Tag::Body {
+					update_resized_mass_props(prev_props, &mass_props_coll, &mut mass_props_rbody, &coll_shape);
+				}
+			}
+
+			if group == Tag::Body {
+				if new_density.is_some() || new_dimension.is_some() {
+					apply_body_mass_props(cfg, &mut mass_props_coll, &mut mass_props_rbody, &coll_shape);
+				}
+			} else if let Some(density) = new_density {
+				set_density(density, &mut mass_props_coll, &mut mass_props_rbody, &coll_shape);
+			}
+
+			if let (false, Some(friction)) = (tire_group, new_friction) {
+				material.friction = friction;
+			}
+			if let Some(restitution) = new_restitution {
+				material.restitution = restitution;
+			}
+		}
+
+		if let (true, Some(friction)) = (tire_group, new_friction) {
+			for (_, mut tire, _, _) in tires.iter_mut().filter(|(_, _, _, tag)| **tag == group) {
+				tire.desc.peak_friction = friction;
+			}
+		}
+
+		}); // ui.vertical
+		}); // ui.collapsing
+	}
+	}); // ui.collapsing
+
+	changes
+}
+
+// body collider has explicit mass properties built from config, rigid body gets the difference like with density
+fn apply_body_mass_props(
+	cfg: &VehicleConfig,
+	mass_props_coll: &mut Mut<ColliderMassPropsComponent>,
+	mass_props_rbody: &mut Mut<RigidBodyMassPropsComponent>,
+	coll_shape: &Mut<ColliderShapeComponent>,
+) {
+	let prev_props = mass_props_coll.mass_properties(&****coll_shape).clone();
+	let new_props = cfg.body_mass_properties();
+	**mass_props_coll = ColliderMassProps::MassProperties(Box::new(new_props)).into();
+	mass_props_rbody.local_mprops -= prev_props;
+	mass_props_rbody.local_mprops += new_props;
+}
+
+fn draw_body_mass_ui(
+	ui: &mut Ui,
+	cfg: &mut VehicleConfig,
+	mass_props_coll: &mut Mut<ColliderMassPropsComponent>,
+	mass_props_rbody: &mut Mut<RigidBodyMassPropsComponent>,
+	coll_shape: &Mut<ColliderShapeComponent>,
+) {
+	let mut changed = false;
+
+	changed |= ui.add(
+		Slider::new(&mut cfg.body_density, 0.01 ..= 1000.0).text("Density"),
+	).changed();
+
+	changed |= ui.add(
+		Slider::new(&mut cfg.body_center_of_mass.x, -2.0 ..= 2.0).text("Center of mass X"),
+	).changed();
+	changed |= ui.add(
+		Slider::new(&mut cfg.body_center_of_mass.y, -2.0 ..= 2.0).text("Center of mass Y"),
+	).changed();
+	changed |= ui.add(
+		Slider::new(&mut cfg.body_center_of_mass.z, -2.0 ..= 2.0).text("Center of mass Z"),
+	).changed();
+
+	let current = mass_props_coll.mass_properties(&****coll_shape);
+
+	let mut mass_override = cfg.body_mass.is_some();
+	if ui.checkbox(&mut mass_override, "Override mass").changed() {
+		cfg.body_mass = if mass_override { Some(current.mass()) } else { None };
+		changed = true;
+	}
+	if let Some(mass) = &mut cfg.body_mass {
+		changed |= ui.add(
+			Slider::new(mass, 1.0 ..= 5000.0).text("Mass"),
+		).changed();
+	}
+
+	let mut inertia_override = cfg.body_inertia.is_some();
+	if ui.checkbox(&mut inertia_override, "Override inertia").changed() {
+		let inertia = current.principal_inertia();
+		cfg.body_inertia = if inertia_override { Some(Vec3::new(inertia.x, inertia.y, inertia.z)) } else { None };
+		changed = true;
+	}
+	if let Some(inertia) = &mut cfg.body_inertia {
+		changed |= ui.add(
+			Slider::new(&mut inertia.x, 1.0 ..= 5000.0).text("Inertia X (roll)"),
+		).changed();
+		changed |= ui.add(
+			Slider::new(&mut inertia.y, 1.0 ..= 5000.0).text("Inertia Y (yaw)"),
+		).changed();
+		changed |= ui.add(
+			Slider::new(&mut inertia.z, 1.0 ..= 5000.0).text("Inertia Z (pitch)"),
+		).changed();
+	}
+
+	if changed {
+		apply_body_mass_props(cfg, mass_props_coll, mass_props_rbody, coll_shape);
+	}
+}
+
 fn draw_body_params_ui_collapsing(
 	ui: &mut Ui,
-	name: &String,
+	cfg: &mut VehicleConfig,
 	mass_props_coll: &mut Mut<ColliderMassPropsComponent>,
 	mass_props_rbody: &mut Mut<RigidBodyMassPropsComponent>,
 	coll_shape: &mut Mut<ColliderShapeComponent>,
@@ -762,7 +1713,7 @@ fn draw_body_params_ui_collapsing(
 ) {
 	ui.collapsing(section_name, |ui| {
 		ui.vertical(|ui| {
-			draw_density_param_ui(ui, name, mass_props_coll, mass_props_rbody, coll_shape);
+			draw_body_mass_ui(ui, cfg, mass_props_coll, mass_props_rbody, coll_shape);
 
 			let shape = coll_shape.make_mut();
 			let cuboid = shape.as_cuboid_mut().unwrap();
@@ -819,10 +1770,18 @@ fn update_ui(
 	mut ui_context	: ResMut<EguiContext>,
 		game		: Res	<Game>,
 	mut vehicle_cfg	: ResMut<VehicleConfig>,
+	mut driving_cfg	: ResMut<DrivingConfig>,
+	mut input_cfg	: ResMut<InputConfig>,
+	mut joints		: ResMut<ImpulseJointSet>,
+	mut vehicles	: Query<&mut Vehicle>,
+	mut drivetrains	: Query<(&mut Drivetrain, &mut Engine, &mut Gearbox, &mut Differentials, &mut Brakes, &mut DrivingAids)>,
+	mut joint_query	: Query<&mut JointHandleComponent>,
+	mut tires		: Query<(&NameComponent, &mut Tire, &WheelState, &Tag)>,
 	mut	query		: Query<(
 		&mut ColliderMassPropsComponent,
 		&mut RigidBodyMassPropsComponent,
 		&mut ColliderShapeComponent,
+		&mut ColliderMaterialComponent,
 		&NameComponent,
 		&Tag
 	)>
@@ -867,15 +1826,178 @@ fn update_ui(
 		}); // ui.vertical
 		}); // ui.collapsing
 
-		let mut RF = vec![];
-		let mut LF = vec![];
-		let mut RR = vec![];
-		let mut LR = vec![];
+		let mut suspension_changed			= false;
+
+		ui.collapsing("Suspension".to_string(), |ui| {
+		ui.vertical(|ui| {
+
+		suspension_changed |= ui.add(
+			Slider::new(&mut vehicle_cfg.susp_stiffness, 0.0 ..= 1000.0)
+				.text("Stiffness"),
+		).changed();
+
+		suspension_changed |= ui.add(
+			Slider::new(&mut vehicle_cfg.susp_damping, 0.0 ..= 100.0)
+				.text("Damping"),
+		).changed();
+
+		suspension_changed |= ui.add(
+			Slider::new(&mut vehicle_cfg.susp_rest_length, 0.0 ..= 1.0)
+				.text("Rest length"),
+		).changed();
+
+		suspension_changed |= ui.add(
+			Slider::new(&mut vehicle_cfg.susp_travel, 0.0 ..= 1.0)
+				.text("Travel"),
+		).changed();
+
+		}); // ui.vertical
+		}); // ui.collapsing
+
+		let mut axle_motor_changed			= false;
+
+		ui.collapsing("Joint motors".to_string(), |ui| {
+		ui.vertical(|ui| {
+
+		axle_motor_changed |= ui.add(
+			Slider::new(&mut driving_cfg.axle_stiffness, 0.0 ..= 100.0)
+				.text("Axle stiffness"),
+		).changed();
+
+		axle_motor_changed |= ui.add(
+			Slider::new(&mut driving_cfg.axle_damping, 0.0 ..= 100.0)
+				.text("Axle damping"),
+		).changed();
+
+		ui.add(
+			Slider::new(&mut driving_cfg.steer_angle, 0.0 ..= 60.0)
+				.text("Steer angle"),
+		);
+
+		ui.add(
+			Slider::new(&mut driving_cfg.steer_stiffness, 0.0 ..= 100.0)
+				.text("Steer stiffness"),
+		);
+
+		ui.add(
+			Slider::new(&mut driving_cfg.steer_damping, 0.0 ..= 100.0)
+				.text("Steer damping"),
+		);
+
+		ui.add(
+			Slider::new(&mut driving_cfg.drive_factor, 0.0 ..= 100.0)
+				.text("Drive factor"),
+		);
+
+		}); // ui.vertical
+		}); // ui.collapsing
+
+		// steered axles and wheel motors pick up new values in accelerate_system on next update
+		if axle_motor_changed {
+			for vehicle in vehicles.iter() {
+				for wheel in vehicle.wheels.iter().filter(|w| !w.steered) {
+					motor_steer(0.0, driving_cfg.axle_stiffness, driving_cfg.axle_damping, wheel.axle_joint, &mut joints, &mut joint_query);
+				}
+			}
+		}
+
+		let mut axle_changed				= false;
+
+		ui.collapsing("Axles".to_string(), |ui| {
+		ui.vertical(|ui| {
+
+		axle_changed |= ui.add(
+			Slider::new(&mut vehicle_cfg.axle_half_size.x, 0.05 ..= 1.0)
+				.text("Axle half size X"),
+		).changed();
+
+		axle_changed |= ui.add(
+			Slider::new(&mut vehicle_cfg.axle_half_size.y, 0.05 ..= 1.0)
+				.text("Axle half size Y"),
+		).changed();
+
+		axle_changed |= ui.add(
+			Slider::new(&mut vehicle_cfg.axle_half_size.z, 0.05 ..= 1.0)
+				.text("Axle half size Z"),
+		).changed();
+
+		axle_changed |= ui.add(
+			Slider::new(&mut vehicle_cfg.wheel_offset, 0.0 ..= 2.0)
+				.text("Wheel offset from axle"),
+		).changed();
+
+		}); // ui.vertical
+		}); // ui.collapsing
+
+		let mut placement_changed			= false;
 
-		for (mass_props_coll, mass_props_rbody, mut coll_shape, name_comp, tag) in query.iter_mut() {
+		ui.collapsing("Wheel placement".to_string(), |ui| {
+		ui.vertical(|ui| {
+
+		for mut vehicle in vehicles.iter_mut() {
+			for wheel in vehicle.wheels.iter_mut() {
+				ui.horizontal(|ui| {
+					ui.label(&wheel.name);
+					placement_changed |= ui.add(egui::DragValue::new(&mut wheel.offset.x).speed(0.01).prefix("x: ")).changed();
+					placement_changed |= ui.add(egui::DragValue::new(&mut wheel.offset.y).speed(0.01).prefix("y: ")).changed();
+					placement_changed |= ui.add(egui::DragValue::new(&mut wheel.offset.z).speed(0.01).prefix("z: ")).changed();
+				});
+			}
+		}
+
+		}); // ui.vertical
+		}); // ui.collapsing
+
+		if suspension_changed {
+			for vehicle in vehicles.iter() {
+				for wheel in vehicle.wheels.iter() {
+					motor_suspension(
+						vehicle_cfg.susp_rest_length,
+						vehicle_cfg.susp_travel,
+						vehicle_cfg.susp_stiffness,
+						vehicle_cfg.susp_damping,
+						wheel.suspension_joint,
+						&mut joints,
+						&mut joint_query
+					);
+				}
+			}
+		}
+
+		ui.collapsing("Steering".to_string(), |ui| {
+		ui.vertical(|ui| {
+
+		ui.add(
+			Slider::new(&mut vehicle_cfg.ackermann_percent, -100.0 ..= 150.0)
+				.text("Ackermann %"),
+		);
+
+		}); // ui.vertical
+		}); // ui.collapsing
+
+		for (mut drivetrain, mut engine, mut gearbox, mut differentials, mut brakes, mut aids) in drivetrains.iter_mut() {
+			drivetrain::draw_drivetrain_ui(ui, &mut drivetrain);
+			engine::draw_engine_ui(ui, &mut engine, &mut gearbox);
+			differential::draw_differentials_ui(ui, &mut differentials);
+			brakes::draw_brakes_ui(ui, &mut brakes);
+			driving_aids::draw_driving_aids_ui(ui, &mut aids);
+		}
+
+		tire::draw_tires_ui(ui, &mut tires);
+
+		input::draw_input_config_ui(ui, &mut input_cfg);
+
+		let group_changes = draw_group_params_ui(ui, &mut vehicle_cfg, &mut query, &mut tires);
+
+		// wheel and axle names look like "<wheel name> Wheel" / "<wheel name> Axle", group them by wheel name
+		let mut wheel_sections: Vec<(String, Vec<_>)> = Vec::new();
+		let mut body_resized: Option<(Vec3, Vec3)> = group_changes.body_resized;
+
+		for (mass_props_coll, mut mass_props_rbody, mut coll_shape, _, name_comp, tag) in query.iter_mut() {
 			let name = &name_comp.name;
+			let prev_props = mass_props_coll.mass_properties(&***coll_shape);
 
-			match tag {
+			let resized = match tag {
 				Tag::FrontWheel => {
 					if front_wh_hh_changed {
 						set_cylinder_hh(&mut coll_shape, vehicle_cfg.front_hh);
@@ -883,6 +2005,7 @@ fn update_ui(
 					if front_wh_r_changed {
 						set_cylinder_r(&mut coll_shape, vehicle_cfg.front_r);
 					}
+					front_wh_hh_changed || front_wh_r_changed
 				},
 				Tag::RearWheel  => {
 					if rear_wh_hh_changed {
@@ -891,29 +2014,70 @@ fn update_ui(
 					if rear_wh_r_changed {
 						set_cylinder_r(&mut coll_shape, vehicle_cfg.rear_r);
 					}
+					rear_wh_hh_changed || rear_wh_r_changed
 				}
-				_ => (),
+				Tag::Axle => {
+					if axle_changed {
+						set_cuboid_half_extents(&mut coll_shape, vehicle_cfg.axle_half_size);
+					}
+					axle_changed
+				}
+				_ => false,
+			};
+
+			if resized {
+				update_resized_mass_props(prev_props, &mass_props_coll, &mut mass_props_rbody, &coll_shape);
 			}
 
 			let to_push = (name, mass_props_coll, mass_props_rbody, coll_shape, tag);
-			if name.starts_with("RF") {
-				RF.push(to_push);
-			} else if name.starts_with("LF") {
-				LF.push(to_push);
-			} else if name.starts_with("RR") {
-				RR.push(to_push);
-			} else if name.starts_with("LR") {
-				LR.push(to_push);
-			} else if name.eq("Body") {
-				// thanks kpreid!
-				let (name, mut mass_props_coll, mut mass_props_rbody, mut coll_shape, _) = to_push;
-				draw_body_params_ui_collapsing(ui, name, &mut mass_props_coll, &mut mass_props_rbody, &mut coll_shape, "Body".to_string());
+			match tag {
+				Tag::Body => {
+					// thanks kpreid!
+					let (_, mut mass_props_coll, mut mass_props_rbody, mut coll_shape, _) = to_push;
+					let old_half_size = coll_shape.as_cuboid().map(|c| Vec3::from(c.half_extents));
+					draw_body_params_ui_collapsing(ui, &mut vehicle_cfg, &mut mass_props_coll, &mut mass_props_rbody, &mut coll_shape, "Body".to_string());
+					let new_half_size = coll_shape.as_cuboid().map(|c| Vec3::from(c.half_extents));
+
+					if let (Some(old), Some(new)) = (old_half_size, new_half_size) {
+						if old != new {
+							body_resized = Some((old, new));
+							vehicle_cfg.body_half_size = new;
+							apply_body_mass_props(&vehicle_cfg, &mut mass_props_coll, &mut mass_props_rbody, &coll_shape);
+						}
+					}
+				},
+				_ => {
+					let section_name = wheel_section_name(name);
+					match wheel_sections.iter_mut().find(|(section, _)| section == section_name) {
+						Some((_, section)) => section.push(to_push),
+						None => wheel_sections.push((section_name.to_string(), vec![to_push])),
+					}
+				}
+			}
+		}
+
+		for (section_name, section) in wheel_sections {
+			draw_single_wheel_params_ui_collapsing(ui, section, section_name);
+		}
+
+		// wheels keep their place relative to body size
+		if let Some((old, new)) = body_resized {
+			for mut vehicle in vehicles.iter_mut() {
+				for wheel in vehicle.wheels.iter_mut() {
+					wheel.offset *= new / old;
+				}
+			}
+		}
+
+		let anchors_changed = suspension_changed || axle_changed || placement_changed || body_resized.is_some()
+			|| front_wh_hh_changed || rear_wh_hh_changed
+			|| group_changes.front_wheels || group_changes.rear_wheels || group_changes.axles;
+
+		if anchors_changed {
+			for vehicle in vehicles.iter() {
+				update_wheel_anchors(vehicle, &vehicle_cfg, &mut joints, &mut joint_query);
 			}
 		}
-		draw_single_wheel_params_ui_collapsing(ui, RF, "RF".to_string());
-		draw_single_wheel_params_ui_collapsing(ui, LF, "LF".to_string());
-		draw_single_wheel_params_ui_collapsing(ui, RR, "RR".to_string());
-		draw_single_wheel_params_ui_collapsing(ui, LR, "LR".to_string());
 	});
 
 // uncomment when we need to catch a closed window
@@ -0,0 +1,188 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_fly_camera::FlyCamera;
+
+use std::path::PathBuf;
+
+use crate::brakes::Brakes;
+use crate::differential::Differentials;
+use crate::driving_aids::DrivingAids;
+use crate::drivetrain::Drivetrain;
+use crate::engine::{Engine, Gearbox};
+use crate::input::InputConfig;
+use crate::reset::SpawnPoint;
+use crate::vehicle_desc::VehicleDesc;
+
+use super::{DrivingConfig, Game, PartQuery, Vehicle, VehicleConfig, capture_vehicle_desc, despawn_vehicle, spawn_vehicle};
+
+// Presets are regular vehicle description files, so any of them can also be passed with --vehicle
+const PRESETS_DIR: &str = "content/presets";
+
+pub enum PresetAction {
+	Save(String),
+	Load(String),
+}
+
+pub struct PresetLibrary {
+	pub dir			: PathBuf,
+	// file names without extension
+	pub names		: Vec<String>,
+	// name typed in for saving
+	pub save_name	: String,
+	// result of last action
+	pub status		: String,
+	// set by ui, handled by preset_system
+	pub action		: Option<PresetAction>,
+	// existing preset Save was clicked for, it is only written over after Overwrite is clicked too
+	pub overwrite	: Option<String>,
+}
+
+impl Default for PresetLibrary {
+	fn default() -> Self {
+		let mut library = Self {
+			dir			: PathBuf::from(PRESETS_DIR),
+			names		: Vec::new(),
+			save_name	: String::new(),
+			status		: String::new(),
+			action		: None,
+			overwrite	: None,
+		};
+		library.refresh();
+		library
+	}
+}
+
+impl PresetLibrary {
+	pub fn refresh(&mut self) {
+		self.names = std::fs::read_dir(&self.dir)
+			.map(|entries| entries
+				.filter_map(|entry| entry.ok().map(|e| e.path()))
+				.filter(|path| path.extension().map_or(false, |ext| ext == "ron"))
+				.filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+				.collect())
+			.unwrap_or_default();
+		self.names.sort();
+	}
+
+	fn path(&self, name: &str) -> PathBuf {
+		self.dir.join(format!("{}.ron", name))
+	}
+}
+
+// names end up as file names, so keep them simple
+fn valid_name(name: &str) -> bool {
+	!name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+pub fn preset_system(
+	mut library		: ResMut<PresetLibrary>,
+	mut game		: ResMut<Game>,
+	mut vehicle_desc: ResMut<VehicleDesc>,
+	mut vehicle_cfg	: ResMut<VehicleConfig>,
+	mut driving_cfg	: ResMut<DrivingConfig>,
+	mut input_cfg	: ResMut<InputConfig>,
+		spawn_point	: Res<SpawnPoint>,
+		vehicles	: Query<(Entity, &Vehicle, &Drivetrain, &Engine, &Gearbox, &Differentials, &Brakes, &DrivingAids)>,
+		parts		: Query<PartQuery>,
+	mut cameras		: Query<&mut FlyCamera>,
+	mut commands	: Commands,
+) {
+	let spawned = game.body.and_then(|body| vehicles.get(body).ok());
+
+	match library.action.take() {
+		None => (),
+		Some(PresetAction::Save(name)) => {
+			let spawned = match spawned {
+				Some(spawned) => spawned,
+				None => { library.status = "no vehicle to save".to_string(); return }
+			};
+
+			let desc = capture_vehicle_desc(&vehicle_desc, &vehicle_cfg, &driving_cfg, &input_cfg, spawned, &parts);
+			let path = library.path(&name);
+			library.status = match desc.save(&path) {
+				Ok(())	=> format!("saved {:?}", path),
+				Err(err)=> err,
+			};
+			library.refresh();
+		},
+		Some(PresetAction::Load(name)) => {
+			let path = library.path(&name);
+			let desc = match VehicleDesc::load(&path) {
+				Ok(desc)	=> desc,
+				Err(err)	=> { library.status = err; return }
+			};
+
+			if let Some((body, vehicle, ..)) = spawned {
+				despawn_vehicle(body, vehicle, &mut commands);
+			}
+
+			*vehicle_cfg	= VehicleConfig::from_desc(&desc);
+			*driving_cfg	= DrivingConfig::from_desc(&desc.joints);
+			*input_cfg		= desc.input.clone();
+
+			let mut spawn_desc = desc.clone();
+			spawn_desc.body.position = spawn_point.position;
+			spawn_vehicle(&mut game, &spawn_desc, &mut commands);
+
+			// later resets start from the loaded preset
+			*vehicle_desc	= desc;
+			library.status	= format!("loaded {:?}", path);
+
+			for mut camera in cameras.iter_mut() {
+				camera.target = game.body;
+			}
+		},
+	}
+}
+
+pub fn draw_presets_ui(
+	mut ui_context	: ResMut<EguiContext>,
+	mut library		: ResMut<PresetLibrary>,
+) {
+	egui::Window::new("Presets").show(ui_context.ctx_mut(), |ui| {
+		for name in library.names.clone() {
+			ui.horizontal(|ui| {
+				if ui.button("Load").clicked() {
+					library.action = Some(PresetAction::Load(name.clone()));
+				}
+				ui.label(&name);
+			});
+		}
+
+		ui.horizontal(|ui| {
+			ui.text_edit_singleline(&mut library.save_name);
+			if ui.button("Save").clicked() {
+				let name = library.save_name.trim().to_string();
+				if !valid_name(&name) {
+					library.status = "preset name can only have letters, digits, _ and -".to_string();
+				} else if library.names.contains(&name) {
+					// built-in presets are tracked files, don't write over them by accident
+					library.overwrite = Some(name);
+				} else {
+					library.action = Some(PresetAction::Save(name));
+				}
+			}
+		});
+
+		if let Some(name) = library.overwrite.clone() {
+			ui.horizontal(|ui| {
+				ui.label(format!("{} already exists", name));
+				if ui.button("Overwrite").clicked() {
+					library.action		= Some(PresetAction::Save(name));
+					library.overwrite	= None;
+				}
+				if ui.button("Cancel").clicked() {
+					library.overwrite	= None;
+				}
+			});
+		}
+
+		if ui.button("Refresh").clicked() {
+			library.refresh();
+		}
+
+		if !library.status.is_empty() {
+			ui.label(&library.status);
+		}
+	});
+}
@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_fly_camera::FlyCamera;
+use nalgebra::{Isometry3, Vector3};
+
+use crate::brakes::Brakes;
+use crate::differential::Differentials;
+use crate::driving_aids::DrivingAids;
+use crate::drivetrain::Drivetrain;
+use crate::engine::{Engine, Gearbox};
+use crate::input::InputConfig;
+use crate::vehicle_desc::VehicleDesc;
+
+use super::{DrivingConfig, Game, PartQuery, Vehicle, VehicleConfig, capture_vehicle_desc, despawn_vehicle, spawn_vehicle};
+
+// where body is placed on reset
+pub struct SpawnPoint {
+	pub position	: Vec3,
+}
+
+impl SpawnPoint {
+	pub fn from_desc(desc: &VehicleDesc) -> Self {
+		Self { position: desc.body.position }
+	}
+}
+
+// set by keys or ui, handled by reset_vehicle_system on the same update
+#[derive(Default)]
+pub struct ResetRequest {
+	// despawn the vehicle and spawn it again at spawn point
+	pub reset		: bool,
+	// put the vehicle back on its wheels where it is
+	pub upright		: bool,
+}
+
+// how high above current position vehicle is put when turned upright so that wheels don't end up in the ground
+const UPRIGHT_LIFT: f32 = 1.0;
+
+pub fn reset_keys_system(
+		key			: Res<Input<KeyCode>>,
+	mut ui_context	: ResMut<EguiContext>,
+	mut request		: ResMut<ResetRequest>,
+) {
+	// Backspace in a ui text field is editing, not a reset
+	if ui_context.ctx_mut().wants_keyboard_input() {
+		return;
+	}
+
+	request.reset	|= key.just_pressed(KeyCode::Back);
+	request.upright	|= key.just_pressed(KeyCode::U);
+}
+
+pub fn reset_vehicle_system(
+	mut request		: ResMut<ResetRequest>,
+	mut game		: ResMut<Game>,
+		vehicle_desc: Res<VehicleDesc>,
+		vehicle_cfg	: Res<VehicleConfig>,
+		driving_cfg	: Res<DrivingConfig>,
+		input_cfg	: Res<InputConfig>,
+		spawn_point	: Res<SpawnPoint>,
+		vehicles	: Query<(Entity, &Vehicle, &Drivetrain, &Engine, &Gearbox, &Differentials, &Brakes, &DrivingAids)>,
+		parts		: Query<PartQuery>,
+	mut bodies		: Query<(&mut RigidBodyPositionComponent, &mut RigidBodyVelocityComponent)>,
+	mut cameras		: Query<&mut FlyCamera>,
+	mut commands	: Commands,
+) {
+	let body = match game.body { Some(body) => body, None => return };
+
+	if request.upright {
+		request.upright = false;
+
+		if let Ok((_, vehicle, ..)) = vehicles.get(body) {
+			upright_vehicle(body, vehicle, &mut bodies);
+		}
+	}
+
+	if !request.reset {
+		return;
+	}
+	request.reset = false;
+
+	// respawned vehicle keeps everything that was tuned on it
+	let mut desc = match vehicles.get(body) {
+		Ok(spawned) => {
+			despawn_vehicle(body, spawned.1, &mut commands);
+			capture_vehicle_desc(&vehicle_desc, &vehicle_cfg, &driving_cfg, &input_cfg, spawned, &parts)
+		},
+		Err(_) => {
+			let mut desc = vehicle_cfg.apply_to_desc(&vehicle_desc);
+			driving_cfg.apply_to_desc(&mut desc.joints);
+			desc
+		},
+	};
+	desc.body.position = spawn_point.position;
+
+	spawn_vehicle(&mut game, &desc, &mut commands);
+
+	for mut camera in cameras.iter_mut() {
+		camera.target = game.body;
+	}
+}
+
+// Keeps heading and rotates the body back to level. Every other part is moved by the same transform so that joints
+// don't have to pull them back violently
+fn upright_vehicle(
+	body			: Entity,
+	vehicle			: &Vehicle,
+	bodies			: &mut Query<(&mut RigidBodyPositionComponent, &mut RigidBodyVelocityComponent)>,
+) {
+	let old = match bodies.get_mut(body) { Ok((pos, _)) => pos.position, Err(_) => return };
+
+	let forward		= old.rotation * Vector3::z();
+	let heading		= forward.x.atan2(forward.z);
+	let new			= Isometry3::new(old.translation.vector + Vector3::y() * UPRIGHT_LIFT, Vector3::y() * heading);
+	let delta		= new * old.inverse();
+
+	let parts		= vehicle.wheels.iter().flat_map(|w| [w.suspension, w.axle, w.wheel]);
+	for entity in std::iter::once(body).chain(parts) {
+		if let Ok((mut pos, mut vel)) = bodies.get_mut(entity) {
+			pos.position		= delta * pos.position;
+			pos.next_position	= pos.position;
+			vel.linvel			= Vector3::zeros();
+			vel.angvel			= Vector3::zeros();
+		}
+	}
+}
+
+pub fn draw_reset_ui(
+	mut ui_context	: ResMut<EguiContext>,
+	mut request		: ResMut<ResetRequest>,
+	mut spawn_point	: ResMut<SpawnPoint>,
+		game		: Res<Game>,
+		bodies		: Query<&RigidBodyPositionComponent>,
+) {
+	egui::Window::new("Vehicle").show(ui_context.ctx_mut(), |ui| {
+		ui.horizontal(|ui| {
+			if ui.button("Reset (Backspace)").clicked() {
+				request.reset = true;
+			}
+			if ui.button("Upright (U)").clicked() {
+				request.upright = true;
+			}
+		});
+
+		ui.horizontal(|ui| {
+			ui.label("Spawn point");
+			ui.add(egui::DragValue::new(&mut spawn_point.position.x).speed(0.1).prefix("x: "));
+			ui.add(egui::DragValue::new(&mut spawn_point.position.y).speed(0.1).prefix("y: "));
+			ui.add(egui::DragValue::new(&mut spawn_point.position.z).speed(0.1).prefix("z: "));
+		});
+
+		if ui.button("Spawn here").clicked() {
+			if let Some(Ok(pos)) = game.body.map(|body| bodies.get(body)) {
+				let translation		= pos.position.translation.vector;
+				spawn_point.position = Vec3::new(translation.x, translation.y + UPRIGHT_LIFT, translation.z);
+			}
+		}
+	});
+}
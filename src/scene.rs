@@ -0,0 +1,246 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use bevy_rapier3d::physics::{JointBuilderComponent, JointHandleComponent};
+use bevy_egui::{egui, EguiContext};
+use bevy_fly_camera::FlyCamera;
+
+use std::path::Path;
+
+use crate::brakes::Brakes;
+use crate::course::CourseDesc;
+use crate::differential::Differentials;
+use crate::driving_aids::DrivingAids;
+use crate::drivetrain::Drivetrain;
+use crate::engine::{Engine, Gearbox};
+use crate::input::InputConfig;
+use crate::surface::SurfaceRegistry;
+use crate::terrain::{ProceduralTerrain, TerrainDesc};
+use crate::vehicle_desc::VehicleDesc;
+
+use super::{DrivingConfig, Game, PartQuery, Vehicle, VehicleConfig, capture_vehicle_desc, spawn_cubes, spawn_ground, spawn_surface_patches, spawn_vehicle};
+
+const PROVING_GROUND: &str = "content/courses/proving_ground.ron";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scene {
+	// flat ground and nothing else
+	Empty,
+	// flat ground with surface patches and a pile of cubes to crash into
+	CubePile,
+	HillClimb,
+	// row of cones to weave between
+	Slalom,
+	ObstacleCourse,
+}
+
+impl Default for Scene {
+	fn default() -> Self {
+		Scene::CubePile
+	}
+}
+
+impl Scene {
+	pub const ALL: [Scene; 5] = [Scene::Empty, Scene::CubePile, Scene::HillClimb, Scene::Slalom, Scene::ObstacleCourse];
+
+	// used on command line
+	pub fn name(&self) -> &'static str {
+		match self {
+			Scene::Empty			=> "empty",
+			Scene::CubePile			=> "cubes",
+			Scene::HillClimb		=> "hills",
+			Scene::Slalom			=> "slalom",
+			Scene::ObstacleCourse	=> "course",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.iter().copied().find(|scene| scene.name() == name)
+	}
+
+	fn terrain(&self) -> TerrainDesc {
+		match self {
+			Scene::HillClimb => TerrainDesc::Procedural(ProceduralTerrain {
+				amplitude	: 12.0,
+				frequency	: 0.015,
+				seed		: 1,
+				..ProceduralTerrain::default()
+			}),
+			_ => TerrainDesc::Flat,
+		}
+	}
+
+	fn course(&self) -> CourseDesc {
+		match self {
+			Scene::ObstacleCourse => CourseDesc::load(Path::new(PROVING_GROUND)).unwrap_or_else(|err| {
+				println!("{}. Spawning no obstacles", err);
+				CourseDesc::default()
+			}),
+			_ => CourseDesc::default(),
+		}
+	}
+}
+
+// What is spawned now. Terrain and course come from the scene unless overridden from command line.
+// Setting respawn despawns the world with the vehicle and spawns it again on next update
+pub struct ActiveScene {
+	pub scene		: Scene,
+	pub terrain		: TerrainDesc,
+	pub course		: CourseDesc,
+	pub respawn		: bool,
+}
+
+impl ActiveScene {
+	pub fn new(scene: Scene) -> Self {
+		Self {
+			scene,
+			terrain		: scene.terrain(),
+			course		: scene.course(),
+			respawn		: false,
+		}
+	}
+}
+
+impl Default for ActiveScene {
+	fn default() -> Self {
+		Self::new(Scene::default())
+	}
+}
+
+pub fn spawn_scene(
+	active			: &ActiveScene,
+	game			: &mut ResMut<Game>,
+	vehicle_desc	: &VehicleDesc,
+	surfaces		: &SurfaceRegistry,
+	meshes			: Option<&mut Assets<Mesh>>,
+	materials		: Option<&mut Assets<StandardMaterial>>,
+	commands		: &mut Commands,
+) {
+	match &active.terrain {
+		TerrainDesc::Flat => {
+			spawn_ground		(game, surfaces, commands);
+		},
+		TerrainDesc::Procedural(terrain) => {
+			let terrain		= terrain.spawn(surfaces, meshes, materials, commands);
+			println!		("terrain Entity ID {:?}", terrain);
+		},
+		TerrainDesc::Heightmap(heightmap) => {
+			match heightmap.spawn(surfaces, meshes, materials, commands) {
+				Ok(terrain)	=> println!("terrain Entity ID {:?}", terrain),
+				Err(err)	=> {
+					println!	("{}. Using flat ground", err);
+					spawn_ground(game, surfaces, commands);
+				},
+			}
+		},
+	}
+
+	let obstacles		= active.course.spawn(surfaces, commands);
+	if !obstacles.is_empty() {
+		println!		("spawned {} obstacle parts", obstacles.len());
+	}
+
+	match active.scene {
+		Scene::CubePile => {
+			spawn_surface_patches(surfaces, commands);
+			spawn_cubes			(commands);
+		},
+		Scene::Slalom => {
+			spawn_cones			(commands);
+		},
+		_ => (),
+	}
+
+	spawn_vehicle		(game, vehicle_desc, commands);
+}
+
+// light dynamic cylinders so that hitting one knocks it over instead of stopping the vehicle
+fn spawn_cones(commands: &mut Commands) {
+	let count			= 10;
+	let spacing			= 12.0;
+	let half_height		= 0.4;
+	let radius			= 0.2;
+
+	for i in 0..count {
+		let rigid_body	= RigidBodyBundle {
+			position	: Vec3::new(0.0, half_height, 15.0 + i as f32 * spacing).into(),
+			..RigidBodyBundle::default()
+		};
+
+		let collider	= ColliderBundle {
+			shape		: ColliderShape::cylinder(half_height, radius).into(),
+			mass_properties: ColliderMassProps::Density(0.5).into(),
+			..ColliderBundle::default()
+		};
+
+		commands
+			.spawn_bundle	(rigid_body)
+			.insert_bundle	(collider)
+			.insert			(ColliderDebugRender { color: Color::ORANGE })
+			.insert			(ColliderPositionSync::Discrete);
+	}
+}
+
+pub fn scene_respawn_system(
+	mut active		: ResMut<ActiveScene>,
+	mut game		: ResMut<Game>,
+		vehicle_desc: Res<VehicleDesc>,
+		vehicle_cfg	: Res<VehicleConfig>,
+		driving_cfg	: Res<DrivingConfig>,
+		input_cfg	: Res<InputConfig>,
+		surfaces	: Res<SurfaceRegistry>,
+	mut meshes		: Option<ResMut<Assets<Mesh>>>,
+	mut materials	: Option<ResMut<Assets<StandardMaterial>>>,
+	// everything physical except camera: ground, obstacles, vehicle parts and joints between them
+		world		: Query<Entity, (Or<(With<ColliderShapeComponent>, With<RigidBodyTypeComponent>, With<JointBuilderComponent>, With<JointHandleComponent>)>, Without<FlyCamera>)>,
+		vehicles	: Query<(Entity, &Vehicle, &Drivetrain, &Engine, &Gearbox, &Differentials, &Brakes, &DrivingAids)>,
+		parts		: Query<PartQuery>,
+	mut cameras		: Query<&mut FlyCamera>,
+	mut commands	: Commands,
+) {
+	if !active.respawn {
+		return;
+	}
+	active.respawn = false;
+
+	for entity in world.iter() {
+		commands.entity(entity).despawn();
+	}
+
+	// vehicle comes back with what was tuned in ui, not as it was loaded
+	let desc = match game.body.and_then(|body| vehicles.get(body).ok()) {
+		Some(spawned) => capture_vehicle_desc(&vehicle_desc, &vehicle_cfg, &driving_cfg, &input_cfg, spawned, &parts),
+		None => {
+			let mut desc = vehicle_cfg.apply_to_desc(&vehicle_desc);
+			driving_cfg.apply_to_desc(&mut desc.joints);
+			desc
+		},
+	};
+
+	println!("spawning scene {:?}", active.scene);
+	spawn_scene(&active, &mut game, &desc, &surfaces, meshes.as_deref_mut(), materials.as_deref_mut(), &mut commands);
+
+	for mut camera in cameras.iter_mut() {
+		camera.target = game.body;
+	}
+}
+
+pub fn draw_scene_ui(
+	mut ui_context	: ResMut<EguiContext>,
+	mut active		: ResMut<ActiveScene>,
+) {
+	egui::Window::new("Scene").show(ui_context.ctx_mut(), |ui| {
+		let mut selected = active.scene;
+		for scene in Scene::ALL.iter() {
+			ui.radio_value(&mut selected, *scene, format!("{:?}", scene));
+		}
+
+		if selected != active.scene {
+			*active			= ActiveScene::new(selected);
+			active.respawn	= true;
+		}
+
+		if ui.button("Respawn").clicked() {
+			active.respawn	= true;
+		}
+	});
+}
@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+
+// Ackermann steering: every steered wheel points at the same turning center lying on the line through the
+// reference (non-steered) axle. steer_angle is the angle of the front-most steered axle centerline, positive angle
+// turns towards +x like revolute joint around y does. ackermann is a fraction: 0 parallel, 1 ideal, negative anti-Ackermann.
+//
+// tan(angle_i) = wheelbase_i * tan(angle) / (wheelbase - x_i * tan(angle))
+pub fn ackermann_angles(steer_angle: f32, ackermann: f32, offsets: &[Vec3], steered: &[bool]) -> Vec<f32> {
+	let reference_z	= reference_axle_z(offsets, steered);
+	let wheelbase	= offsets.iter().zip(steered)
+		.filter(|(_, &s)| s)
+		.map(|(o, _)| o.z - reference_z)
+		.fold(0.0f32, |max, l| if l.abs() > max.abs() { l } else { max });
+
+	let tan = steer_angle.to_radians().tan();
+
+	offsets.iter().zip(steered).map(|(offset, &s)| {
+		if !s || wheelbase.abs() < f32::EPSILON {
+			return steer_angle;
+		}
+
+		let wheelbase_i	= offset.z - reference_z;
+		// plain atan, atan2 would flip the angle by 180 degrees whenever wheelbase is negative, like with rear steering
+		let ideal		= (wheelbase_i * tan / (wheelbase - offset.x * tan)).atan().to_degrees();
+
+		steer_angle + ackermann * (ideal - steer_angle)
+	}).collect()
+}
+
+// middle of non-steered axles. If everything is steered, middle of the vehicle
+fn reference_axle_z(offsets: &[Vec3], steered: &[bool]) -> f32 {
+	let fixed: Vec<f32> = offsets.iter().zip(steered).filter(|(_, &s)| !s).map(|(o, _)| o.z).collect();
+	let all: Vec<f32>	= offsets.iter().map(|o| o.z).collect();
+
+	let zs = if fixed.is_empty() { &all } else { &fixed };
+	if zs.is_empty() {
+		return 0.0;
+	}
+
+	zs.iter().sum::<f32>() / zs.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TRACK: f32 = 0.8;
+	const HALF_BASE: f32 = 1.5;
+
+	fn wheels(front_z: f32, rear_z: f32) -> Vec<Vec3> {
+		vec![
+			Vec3::new( TRACK, 0.0, front_z),
+			Vec3::new(-TRACK, 0.0, front_z),
+			Vec3::new( TRACK, 0.0, rear_z),
+			Vec3::new(-TRACK, 0.0, rear_z),
+		]
+	}
+
+	fn ideal(wheelbase: f32, x: f32, steer_angle: f32) -> f32 {
+		let tan = steer_angle.to_radians().tan();
+		(wheelbase * tan / (wheelbase - x * tan)).atan().to_degrees()
+	}
+
+	#[test]
+	fn front_steer_turns_inner_wheel_more() {
+		let angles = ackermann_angles(20.0, 1.0, &wheels(HALF_BASE, -HALF_BASE), &[true, true, false, false]);
+
+		assert!(angles[0] > 20.0 && angles[1] < 20.0 && angles[1] > 0.0);
+		assert!((angles[0] - ideal(2.0 * HALF_BASE, TRACK, 20.0)).abs() < 1e-4);
+		assert!((angles[1] - ideal(2.0 * HALF_BASE, -TRACK, 20.0)).abs() < 1e-4);
+		assert_eq!(&angles[2..], &[20.0, 20.0]);
+	}
+
+	#[test]
+	fn zero_ackermann_is_parallel() {
+		let angles = ackermann_angles(20.0, 0.0, &wheels(HALF_BASE, -HALF_BASE), &[true, true, false, false]);
+
+		assert!(angles.iter().all(|a| (a - 20.0).abs() < 1e-4));
+	}
+
+	#[test]
+	fn rear_steer_keeps_wheels_pointing_forward() {
+		let angles = ackermann_angles(20.0, 1.0, &wheels(HALF_BASE, -HALF_BASE), &[false, false, true, true]);
+
+		for angle in &angles[2..] {
+			assert!(*angle > 0.0 && *angle < 45.0, "rear wheel angle {}", angle);
+		}
+		assert!((angles[2] - ideal(-2.0 * HALF_BASE, TRACK, 20.0)).abs() < 1e-4);
+		assert!((angles[3] - ideal(-2.0 * HALF_BASE, -TRACK, 20.0)).abs() < 1e-4);
+	}
+
+	#[test]
+	fn four_wheel_steer_turns_rear_the_other_way() {
+		let angles = ackermann_angles(20.0, 1.0, &wheels(HALF_BASE, -HALF_BASE), &[true; 4]);
+
+		for angle in &angles[..2] {
+			assert!(*angle > 0.0 && *angle < 45.0, "front wheel angle {}", angle);
+		}
+		for angle in &angles[2..] {
+			assert!(*angle < 0.0 && *angle > -45.0, "rear wheel angle {}", angle);
+		}
+
+		// turning center is level with the middle of the vehicle, so rear wheels mirror front ones
+		assert!((angles[0] + angles[2]).abs() < 1e-4);
+		assert!((angles[1] + angles[3]).abs() < 1e-4);
+	}
+}
@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use bevy_rapier3d::physics::IntoHandle;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+use super::{Vehicle, WheelState};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Surface {
+	Asphalt,
+	Gravel,
+	Mud,
+	Ice,
+	Grass,
+}
+
+// Attached to any collider that should behave like a given surface. Friction is a grip factor relative to asphalt:
+// it's used as is for regular colliders and multiplies tire friction for wheels
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SurfaceMaterial {
+	pub kind				: Surface,
+	pub friction			: f32,
+	pub restitution			: f32,
+	// rolling resistance coefficient, resistive torque on a wheel is this * normal load * radius
+	pub rolling_resistance	: f32,
+	pub color				: Color,
+}
+
+pub struct SurfaceRegistry {
+	pub materials			: HashMap<Surface, SurfaceMaterial>,
+}
+
+impl Default for SurfaceRegistry {
+	fn default() -> Self {
+		let material = |kind, friction, restitution, rolling_resistance, color| SurfaceMaterial {
+			kind,
+			friction,
+			restitution,
+			rolling_resistance,
+			color,
+		};
+
+		let materials = [
+			material(Surface::Asphalt,	1.0,	0.1,	0.015,	Color::rgb(0.3, 0.3, 0.3)),
+			material(Surface::Gravel,	0.6,	0.05,	0.05,	Color::rgb(0.6, 0.55, 0.5)),
+			material(Surface::Mud,		0.4,	0.0,	0.15,	Color::rgb(0.4, 0.25, 0.1)),
+			material(Surface::Ice,		0.1,	0.05,	0.01,	Color::rgb(0.8, 0.9, 1.0)),
+			material(Surface::Grass,	0.5,	0.1,	0.08,	Color::rgb(0.3, 0.6, 0.2)),
+		];
+
+		Self { materials: materials.iter().map(|m| (m.kind, *m)).collect() }
+	}
+}
+
+impl SurfaceRegistry {
+	pub fn get(&self, kind: Surface) -> SurfaceMaterial {
+		self.materials[&kind]
+	}
+
+	// collider material is set too so that contacts rapier resolves without tire model behave the same way
+	pub fn collider_material(&self, kind: Surface) -> ColliderMaterial {
+		let material = self.get(kind);
+		ColliderMaterial {
+			friction	: material.friction,
+			restitution	: material.restitution,
+			..Default::default()
+		}
+	}
+}
+
+// flat cuboid of given surface lying on top of the ground
+pub fn spawn_surface_patch(
+	kind			: Surface,
+	center			: Vec3,
+	half_size		: Vec2,
+	registry		: &SurfaceRegistry,
+	commands		: &mut Commands,
+) -> Entity {
+	let patch_height	= 0.1;
+	let material		= registry.get(kind);
+
+	let patch_bundle	= ColliderBundle {
+		shape			: ColliderShape::cuboid(half_size.x, patch_height, half_size.y).into(),
+		// just a tiny bit above the ground so that wheels touch the patch
+		position		: (center + Vec3::new(0.0, -patch_height + 0.001, 0.0)).into(),
+		material		: registry.collider_material(kind).into(),
+		..ColliderBundle::default()
+	};
+
+	commands
+		.spawn_bundle	(patch_bundle)
+		.insert			(ColliderDebugRender { color: material.color })
+		.insert			(ColliderPositionSync::Discrete)
+		.insert			(material)
+		.id				()
+}
+
+// Looks up which surface every wheel is touching and how hard it is pressed into it,
+// then adds rolling resistance of that surface to the wheel's resistive torque
+pub fn rolling_resistance_system(
+		integration	: Res<IntegrationParameters>,
+		narrow_phase: Res<NarrowPhase>,
+		vehicles	: Query<&Vehicle>,
+		surfaces	: Query<&SurfaceMaterial>,
+		shapes		: Query<&ColliderShapeComponent>,
+	mut wheel_states: Query<&mut WheelState>,
+) {
+	for vehicle in vehicles.iter() {
+		for wheel in vehicle.wheels.iter() {
+			let handle		= wheel.wheel.handle();
+			let radius		= shapes.get(wheel.wheel).ok()
+				.and_then(|shape| shape.as_cylinder().map(|c| c.radius))
+				.unwrap_or(0.0);
+
+			let mut surface	= None;
+			let mut torque	= 0.0;
+
+			for pair in narrow_phase.contacts_with(handle) {
+				if !pair.has_any_active_contact {
+					continue;
+				}
+
+				let other = if pair.collider1 == handle { pair.collider2 } else { pair.collider1 };
+				let material = match surfaces.get(other.entity()) { Ok(m) => m, Err(_) => continue };
+
+				let impulse: f32 = pair.manifolds.iter()
+					.flat_map(|manifold| manifold.points.iter())
+					.map(|point| point.data.impulse)
+					.sum();
+
+				let load	= impulse / integration.dt;
+				torque		+= material.rolling_resistance * load * radius;
+				surface		= Some(material.kind);
+			}
+
+			if let Ok(mut state) = wheel_states.get_mut(wheel.wheel) {
+				state.surface		= surface;
+				state.brake_torque	+= torque;
+			}
+		}
+	}
+}
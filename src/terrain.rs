@@ -0,0 +1,291 @@
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy_rapier3d::prelude::*;
+use nalgebra::{vector, DMatrix};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use std::path::{Path, PathBuf};
+
+use crate::surface::{Surface, SurfaceRegistry};
+use crate::vehicle_desc::load_ron;
+
+// Rolling hills from seeded fractal value noise. Same seed gives the same terrain every run
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ProceduralTerrain {
+	// full size by x and z, meters
+	pub size		: f32,
+	// number of cells by each axis
+	pub resolution	: usize,
+	// max height of the first octave, meters
+	pub amplitude	: f32,
+	// noise lattice cells per meter of the first octave
+	pub frequency	: f32,
+	// every next octave has twice the frequency and half the amplitude
+	pub octaves		: u32,
+	pub seed		: u64,
+	pub surface		: Surface,
+}
+
+impl Default for ProceduralTerrain {
+	fn default() -> Self {
+		Self {
+			size		: 400.0,
+			resolution	: 128,
+			amplitude	: 6.0,
+			frequency	: 0.02,
+			octaves		: 4,
+			seed		: 0,
+			surface		: Surface::Grass,
+		}
+	}
+}
+
+// Grayscale image where black is min_height and white is max_height. Image x goes along world x and image y along z
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct HeightmapTerrain {
+	pub path		: PathBuf,
+	// full size by x and z, meters. Image is stretched to fit
+	pub size		: f32,
+	pub min_height	: f32,
+	pub max_height	: f32,
+	pub surface		: Surface,
+}
+
+impl Default for HeightmapTerrain {
+	fn default() -> Self {
+		Self {
+			path		: PathBuf::from("content/terrains/bowl.png"),
+			size		: 200.0,
+			min_height	: 0.0,
+			max_height	: 15.0,
+			surface		: Surface::Asphalt,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum TerrainDesc {
+	// flat ground with surface patches
+	Flat,
+	Procedural(ProceduralTerrain),
+	Heightmap(HeightmapTerrain),
+}
+
+impl Default for TerrainDesc {
+	fn default() -> Self {
+		TerrainDesc::Flat
+	}
+}
+
+impl TerrainDesc {
+	// "flat", "hills", path to a .png heightmap or path to a .ron file with TerrainDesc
+	pub fn from_arg(arg: &str) -> Result<Self, String> {
+		match arg {
+			"flat"	=> Ok(TerrainDesc::Flat),
+			"hills"	=> Ok(TerrainDesc::Procedural(ProceduralTerrain::default())),
+			path if path.ends_with(".png") => Ok(TerrainDesc::Heightmap(HeightmapTerrain {
+				path	: PathBuf::from(path),
+				..HeightmapTerrain::default()
+			})),
+			path	=> {
+				let path = Path::new(path);
+				load_ron(path)
+			},
+		}
+	}
+
+	pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+		if let (TerrainDesc::Procedural(terrain), Some(seed)) = (&mut self, seed) {
+			terrain.seed = seed;
+		}
+		self
+	}
+}
+
+// random values on a lattice, smoothly interpolated in between
+struct ValueNoise {
+	lattice			: Vec<f32>,
+	lattice_size	: usize,
+}
+
+impl ValueNoise {
+	fn new(lattice_size: usize, rng: &mut StdRng) -> Self {
+		let lattice_size = lattice_size.max(2);
+		Self {
+			lattice			: (0..lattice_size * lattice_size).map(|_| rng.gen_range(-1.0..=1.0)).collect(),
+			lattice_size,
+		}
+	}
+
+	fn value(&self, x: usize, z: usize) -> f32 {
+		let n = self.lattice_size;
+		self.lattice[(z % n) * n + (x % n)]
+	}
+
+	// x and z in lattice cells
+	fn sample(&self, x: f32, z: f32) -> f32 {
+		let (x0, z0)	= (x.floor(), z.floor());
+		let (tx, tz)	= (x - x0, z - z0);
+		// smoothstep so that slopes don't break at lattice points
+		let (sx, sz)	= (tx * tx * (3.0 - 2.0 * tx), tz * tz * (3.0 - 2.0 * tz));
+		let (x0, z0)	= (x0 as usize, z0 as usize);
+
+		let top			= self.value(x0, z0) * (1.0 - sx) + self.value(x0 + 1, z0) * sx;
+		let bottom		= self.value(x0, z0 + 1) * (1.0 - sx) + self.value(x0 + 1, z0 + 1) * sx;
+		top * (1.0 - sz) + bottom * sz
+	}
+}
+
+impl ProceduralTerrain {
+	// heights in meters, rows go along z and columns along x like rapier heightfield expects
+	pub fn generate_heights(&self) -> DMatrix<Real> {
+		let mut rng		= StdRng::seed_from_u64(self.seed);
+		let vertices	= self.resolution.max(1) + 1;
+		let step		= self.size / self.resolution.max(1) as f32;
+
+		let octaves: Vec<(ValueNoise, f32, f32)> = (0..self.octaves).map(|octave| {
+			let frequency	= self.frequency * 2.0f32.powi(octave as i32);
+			let amplitude	= self.amplitude * 0.5f32.powi(octave as i32);
+			let lattice		= (self.size * frequency).ceil() as usize + 2;
+			(ValueNoise::new(lattice, &mut rng), frequency, amplitude)
+		}).collect();
+
+		DMatrix::from_fn(vertices, vertices, |i, j| {
+			let (x, z) = (j as f32 * step, i as f32 * step);
+			octaves.iter().map(|(noise, frequency, amplitude)| noise.sample(x * frequency, z * frequency) * amplitude).sum()
+		})
+	}
+}
+
+impl ProceduralTerrain {
+	pub fn spawn(
+		&self,
+		registry	: &SurfaceRegistry,
+		meshes		: Option<&mut Assets<Mesh>>,
+		materials	: Option<&mut Assets<StandardMaterial>>,
+		commands	: &mut Commands,
+	) -> Entity {
+		spawn_heightfield(self.generate_heights(), self.size, self.surface, registry, meshes, materials, commands)
+	}
+}
+
+impl HeightmapTerrain {
+	// heights in meters, rows go along image y (world z) and columns along image x (world x)
+	pub fn load_heights(&self) -> Result<DMatrix<Real>, String> {
+		let image = image::open(&self.path).map_err(|e| format!("failed to load heightmap {:?}: {}", self.path, e))?.into_luma16();
+		let (width, height) = image.dimensions();
+		if width < 2 || height < 2 {
+			return Err(format!("heightmap {:?} is too small: {}x{}", self.path, width, height));
+		}
+
+		let range = self.max_height - self.min_height;
+		Ok(DMatrix::from_fn(height as usize, width as usize, |i, j| {
+			let value = image.get_pixel(j as u32, i as u32)[0] as f32 / u16::MAX as f32;
+			self.min_height + value * range
+		}))
+	}
+
+	pub fn spawn(
+		&self,
+		registry	: &SurfaceRegistry,
+		meshes		: Option<&mut Assets<Mesh>>,
+		materials	: Option<&mut Assets<StandardMaterial>>,
+		commands	: &mut Commands,
+	) -> Result<Entity, String> {
+		let heights = self.load_heights()?;
+		Ok(spawn_heightfield(heights, self.size, self.surface, registry, meshes, materials, commands))
+	}
+}
+
+// Spawns heightfield collider centered at origin and shifted so that height at the center is 0, so vehicle spawns
+// on the surface no matter the seed. Render mesh is only built when there is something to render it with
+pub fn spawn_heightfield(
+	heights			: DMatrix<Real>,
+	size			: f32,
+	surface			: Surface,
+	registry		: &SurfaceRegistry,
+	meshes			: Option<&mut Assets<Mesh>>,
+	materials		: Option<&mut Assets<StandardMaterial>>,
+	commands		: &mut Commands,
+) -> Entity {
+	let center_height	= heights[(heights.nrows() / 2, heights.ncols() / 2)];
+	let offset			= Vec3::new(0.0, -center_height, 0.0);
+
+	let mesh			= heightfield_mesh(&heights, size);
+
+	// heights are already in meters, so vertical scale is 1
+	let collider		= ColliderBundle {
+		shape			: ColliderShape::heightfield(heights, vector![size, 1.0, size]).into(),
+		position		: offset.into(),
+		material		: registry.collider_material(surface).into(),
+		..ColliderBundle::default()
+	};
+
+	let terrain = commands
+		.spawn_bundle	(collider)
+		.insert			(registry.get(surface))
+		.id				();
+
+	if let (Some(meshes), Some(materials)) = (meshes, materials) {
+		commands.entity(terrain).insert_bundle(PbrBundle {
+			mesh		: meshes.add(mesh),
+			material	: materials.add(registry.get(surface).color.into()),
+			transform	: Transform::from_translation(offset),
+			..Default::default()
+		});
+	}
+
+	terrain
+}
+
+// same layout rapier uses for heightfields: centered at origin, columns along x, rows along z
+fn heightfield_mesh(heights: &DMatrix<Real>, size: f32) -> Mesh {
+	let (rows, cols)	= (heights.nrows(), heights.ncols());
+	let vertex			= |i: usize, j: usize| Vec3::new(
+		(j as f32 / (cols - 1) as f32 - 0.5) * size,
+		heights[(i, j)],
+		(i as f32 / (rows - 1) as f32 - 0.5) * size,
+	);
+
+	let mut positions	= Vec::with_capacity(rows * cols);
+	let mut normals		= Vec::with_capacity(rows * cols);
+	let mut uvs			= Vec::with_capacity(rows * cols);
+
+	for i in 0..rows {
+		for j in 0..cols {
+			// central differences, clamped at the borders
+			let left	= vertex(i, j.saturating_sub(1));
+			let right	= vertex(i, (j + 1).min(cols - 1));
+			let back	= vertex(i.saturating_sub(1), j);
+			let front	= vertex((i + 1).min(rows - 1), j);
+			let normal	= (front - back).cross(right - left).normalize_or_zero();
+
+			positions.push(vertex(i, j).to_array());
+			normals.push(normal.to_array());
+			uvs.push([j as f32 / (cols - 1) as f32, i as f32 / (rows - 1) as f32]);
+		}
+	}
+
+	let mut indices		= Vec::with_capacity((rows - 1) * (cols - 1) * 6);
+	for i in 0..rows - 1 {
+		for j in 0..cols - 1 {
+			let a = (i * cols + j) as u32;
+			let b = a + 1;
+			let c = a + cols as u32;
+			let d = c + 1;
+			indices.extend_from_slice(&[a, c, b, b, c, d]);
+		}
+	}
+
+	let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+	mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+	mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+	mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+	mesh.set_indices(Some(Indices::U32(indices)));
+	mesh
+}
@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use bevy_rapier3d::physics::{ColliderComponentsSet, RigidBodyComponentsSet};
+use bevy_egui::egui::{Slider, Ui};
+use serde::{Deserialize, Serialize};
+
+use super::{NameComponent, Tag, WheelState};
+use crate::surface::SurfaceMaterial;
+
+// what contact hook gets to look at for both colliders of a contact pair
+pub type ContactQuery<'a> = (Option<&'a Tire>, Option<&'a SurfaceMaterial>);
+
+// Simplified Pacejka "magic formula" over combined slip. Rapier friction is a limit on tangential impulse,
+// so up to the curve peak tire gets peak friction and past the peak friction falls towards sliding value.
+// That's what makes a locked or spinning wheel lose grip and a car drift or understeer.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct TireDesc {
+	// D, peak friction coefficient
+	pub peak_friction		: f32,
+	// B for slip ratio and slip angle, how quickly force builds up with slip
+	pub longitudinal_stiffness	: f32,
+	pub lateral_stiffness	: f32,
+	// C and E, shape of the curve past the peak
+	pub shape				: f32,
+	pub curvature			: f32,
+}
+
+impl Default for TireDesc {
+	fn default() -> Self {
+		Self {
+			peak_friction			: 1.2,
+			longitudinal_stiffness	: 10.0,
+			lateral_stiffness		: 8.0,
+			shape					: 1.9,
+			curvature				: 0.97,
+		}
+	}
+}
+
+// combined slip past which the curve is considered flat, so that peak search gives up on curves that never peak
+const MAX_PEAK_SLIP: f32 = 1000.0;
+
+impl TireDesc {
+	// Curvature above 1 makes the curve rise again far past the peak, so it's limited to 1.
+	// Past c * atan(...) = pi the curve would rise again too, so sliding friction stays at 0 there
+	fn magic_formula(&self, slip: f32) -> f32 {
+		let (c, e) = (self.shape, self.curvature.min(1.0));
+		(c * (slip - e * (slip - slip.atan())).atan()).min(std::f32::consts::PI).sin()
+	}
+
+	// Slip where magic formula peaks: c * atan(g(slip)) = pi/2 with g(slip) = slip - e * (slip - atan(slip)).
+	// g only grows with slip, so the root is found by bisection. Infinity when the curve never peaks
+	pub fn peak_slip(&self) -> f32 {
+		if self.shape <= 1.0 {
+			return f32::INFINITY;
+		}
+
+		let e		= self.curvature.min(1.0);
+		let g		= |slip: f32| slip - e * (slip - slip.atan());
+		let target	= (std::f32::consts::FRAC_PI_2 / self.shape).tan();
+
+		let mut hi	= 1.0;
+		while g(hi) < target {
+			hi *= 2.0;
+			if hi > MAX_PEAK_SLIP {
+				return f32::INFINITY;
+			}
+		}
+
+		let mut lo	= 0.0;
+		for _ in 0..32 {
+			let mid = (lo + hi) / 2.0;
+			if g(mid) < target { lo = mid } else { hi = mid }
+		}
+		hi
+	}
+
+	// friction coefficient for slip ratio and slip angle (radians)
+	pub fn friction(&self, slip_ratio: f32, slip_angle: f32) -> f32 {
+		let x		= self.longitudinal_stiffness * slip_ratio;
+		let y		= self.lateral_stiffness * slip_angle.tan();
+		let slip	= (x * x + y * y).sqrt();
+
+		if slip <= self.peak_slip() {
+			self.peak_friction
+		} else {
+			self.peak_friction * self.magic_formula(slip).max(0.0)
+		}
+	}
+}
+
+// Lives on the wheel entity, contact hook reads friction from here
+#[derive(Component, Clone, Debug)]
+pub struct Tire {
+	pub desc		: TireDesc,
+	pub friction	: f32,
+}
+
+impl Tire {
+	pub fn new(desc: &TireDesc) -> Self {
+		Self { desc: desc.clone(), friction: desc.peak_friction }
+	}
+}
+
+pub fn tire_system(
+	mut tires		: Query<(&mut Tire, &WheelState)>,
+) {
+	for (mut tire, state) in tires.iter_mut() {
+		tire.friction = tire.desc.friction(state.slip_ratio, state.slip_angle);
+	}
+}
+
+pub struct TireContactHook;
+
+// Tire friction replaces whatever rapier combined for wheel contacts, scaled by grip of the surface wheel is on
+impl<'a> PhysicsHooksWithQuery<ContactQuery<'a>> for TireContactHook {
+	fn modify_solver_contacts(
+		&self,
+		context	: &mut ContactModificationContext<RigidBodyComponentsSet, ColliderComponentsSet>,
+		query	: &Query<ContactQuery<'a>>,
+	) {
+		let (tire1, surface1) = query.get(context.collider1.entity()).unwrap_or((None, None));
+		let (tire2, surface2) = query.get(context.collider2.entity()).unwrap_or((None, None));
+
+		let (tire, surface) = match (tire1, tire2) {
+			(Some(tire), None)	=> (tire.friction, surface2),
+			(None, Some(tire))	=> (tire.friction, surface1),
+			(Some(tire1), Some(tire2)) => ((tire1.friction * tire2.friction).sqrt(), None),
+			(None, None)		=> return,
+		};
+
+		let friction = tire * surface.map_or(1.0, |s| s.friction);
+
+		for solver_contact in context.solver_contacts.iter_mut() {
+			solver_contact.friction = friction;
+		}
+	}
+}
+
+pub fn draw_tires_ui(ui: &mut Ui, tires: &mut Query<(&NameComponent, &mut Tire, &WheelState, &Tag)>) {
+	ui.collapsing("Tires".to_string(), |ui| {
+	ui.vertical(|ui| {
+
+	for (name, mut tire, state, _) in tires.iter_mut() {
+		ui.collapsing(name.name.clone(), |ui| {
+			ui.label(format!("slip ratio {:.2} slip angle {:.1} friction {:.2} on {:?}", state.slip_ratio, state.slip_angle.to_degrees(), tire.friction, state.surface));
+
+			let desc = &mut tire.desc;
+			ui.add(Slider::new(&mut desc.peak_friction, 0.1 ..= 3.0).text("Peak friction"));
+			ui.add(Slider::new(&mut desc.longitudinal_stiffness, 1.0 ..= 30.0).text("Longitudinal stiffness"));
+			ui.add(Slider::new(&mut desc.lateral_stiffness, 1.0 ..= 30.0).text("Lateral stiffness"));
+			ui.add(Slider::new(&mut desc.shape, 1.0 ..= 3.0).text("Shape"));
+			ui.add(Slider::new(&mut desc.curvature, -2.0 ..= 1.0).text("Curvature"));
+		});
+	}
+
+	}); // ui.vertical
+	}); // ui.collapsing
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// friction over slip ratio from 0 to 2 with slip angle 0
+	fn curve(desc: &TireDesc) -> Vec<f32> {
+		(0..=20000).map(|i| desc.friction(i as f32 * 0.0001, 0.0)).collect()
+	}
+
+	fn assert_falls_smoothly_past_peak(desc: &TireDesc) {
+		let curve = curve(desc);
+		assert_eq!(curve[0], desc.peak_friction);
+
+		for pair in curve.windows(2) {
+			assert!(pair[1] <= pair[0] + 1e-5, "friction grows from {} to {} for {:?}", pair[0], pair[1], desc);
+			assert!(pair[0] - pair[1] < 0.01, "friction jumps from {} to {} for {:?}", pair[0], pair[1], desc);
+		}
+	}
+
+	#[test]
+	fn default_curve_falls_smoothly_past_peak() {
+		let desc = TireDesc::default();
+		assert_falls_smoothly_past_peak(&desc);
+
+		// locked wheel slides with less grip than peak
+		assert!(desc.friction(-1.0, 0.0) < desc.peak_friction * 0.95);
+	}
+
+	#[test]
+	fn other_shapes_fall_smoothly_past_peak() {
+		for (shape, curvature) in [(1.3, 0.0), (1.65, -1.0), (2.5, 0.5), (1.9, 1.5)] {
+			assert_falls_smoothly_past_peak(&TireDesc { shape, curvature, ..TireDesc::default() });
+		}
+	}
+
+	#[test]
+	fn peak_is_where_magic_formula_peaks() {
+		let desc = TireDesc::default();
+		let peak = desc.peak_slip();
+
+		assert!((desc.magic_formula(peak) - 1.0).abs() < 1e-4);
+		assert!(desc.magic_formula(peak * 0.9) < 1.0);
+		assert!(desc.magic_formula(peak * 1.1) < 1.0);
+	}
+}
@@ -0,0 +1,310 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::path::Path;
+
+use crate::brakes::Brakes;
+use crate::differential::DiffSettings;
+use crate::driving_aids::DrivingAids;
+use crate::drivetrain::Drivetrain;
+use crate::engine::{Engine, Gearbox};
+use crate::input::InputConfig;
+use crate::tire::TireDesc;
+
+// whole file as text with path in the error, so that it can be printed as is
+pub fn read_file(path: &Path) -> Result<String, String> {
+	std::fs::read_to_string(path).map_err(|e| format!("failed to read {:?}: {}", path, e))
+}
+
+// every description file (vehicle, terrain, course) is RON
+pub fn load_ron<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+	let text = read_file(path)?;
+	ron::from_str(&text).map_err(|e| format!("failed to parse {:?}: {}", path, e))
+}
+
+// Serializable vehicle description consumed by spawn_vehicle. Default values reproduce the original hardcoded wagon.
+
+// friction and restitution of a part collider, defaults are the same as rapier ones
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct MaterialDesc {
+	pub friction	: f32,
+	pub restitution	: f32,
+}
+
+impl Default for MaterialDesc {
+	fn default() -> Self {
+		Self {
+			friction	: 0.5,
+			restitution	: 0.0,
+		}
+	}
+}
+
+impl MaterialDesc {
+	pub fn from_collider(material: &ColliderMaterial) -> Self {
+		Self {
+			friction	: material.friction,
+			restitution	: material.restitution,
+		}
+	}
+
+	pub fn collider_material(&self) -> ColliderMaterial {
+		ColliderMaterial {
+			friction	: self.friction,
+			restitution	: self.restitution,
+			..ColliderMaterial::default()
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BodyDesc {
+	pub position	: Vec3,
+	pub half_size	: Vec3,
+	pub density		: f32,
+	// offset from box center, lower is harder to roll over
+	pub center_of_mass	: Vec3,
+	// replaces mass computed from density and size, kg
+	pub mass		: Option<f32>,
+	// replaces principal inertia of the box around body x, y, z, kg*m^2
+	pub inertia		: Option<Vec3>,
+	pub material	: MaterialDesc,
+}
+
+impl Default for BodyDesc {
+	fn default() -> Self {
+		Self {
+			position	: Vec3::new(0.0, 5.5, 0.0),
+			half_size	: Vec3::new(0.5, 0.5, 1.0),
+			density		: 10.0, // joints like it when there is an hierarchy of masses and we want body to be the heaviest
+			center_of_mass	: Vec3::ZERO,
+			mass		: None,
+			inertia		: None,
+			material	: MaterialDesc::default(),
+		}
+	}
+}
+
+impl BodyDesc {
+	pub fn mass_properties(&self) -> MassProperties {
+		body_mass_properties(self.half_size, self.density, self.center_of_mass, self.mass, self.inertia)
+	}
+}
+
+// Box mass properties with optional overrides. Without inertia override inertia of the box is scaled with mass
+pub fn body_mass_properties(half_size: Vec3, density: f32, center_of_mass: Vec3, mass: Option<f32>, inertia: Option<Vec3>) -> MassProperties {
+	let from_box	= MassProperties::from_cuboid(density, vector![half_size.x, half_size.y, half_size.z]);
+	let box_mass	= from_box.mass();
+	let mass		= mass.unwrap_or(box_mass);
+	let inertia		= match inertia {
+		Some(inertia)	=> vector![inertia.x, inertia.y, inertia.z],
+		None			=> from_box.principal_inertia() * (mass / box_mass),
+	};
+
+	MassProperties::new(point![center_of_mass.x, center_of_mass.y, center_of_mass.z], mass, inertia)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AxleDesc {
+	pub half_size	: Vec3,
+	pub density		: f32,
+	// distance between axle and wheel by x axis, mirrored for the left side
+	pub wheel_offset: f32,
+	pub material	: MaterialDesc,
+}
+
+impl Default for AxleDesc {
+	fn default() -> Self {
+		Self {
+			half_size	: Vec3::new(0.1, 0.2, 0.1),
+			density		: 1000.0,
+			wheel_offset: 0.8,
+			material	: MaterialDesc::default(),
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct WheelDesc {
+	pub name		: String,
+	// axle placement relative to body center
+	pub offset		: Vec3,
+	pub radius		: f32,
+	pub half_width	: f32,
+	pub density		: f32,
+	// replaces mass computed from density and size, kg. Wheel keeps it when resized
+	pub mass		: Option<f32>,
+	// wheel contacts take friction from the tire, so only restitution matters here
+	pub material	: MaterialDesc,
+	// front wheels are grouped under Tag::FrontWheel, the rest under Tag::RearWheel
+	pub front		: bool,
+	pub steered		: bool,
+	pub driven		: bool,
+	pub tire		: TireDesc,
+
+	// axle of this wheel tuned apart from the others, None to use AxleDesc values
+	pub axle_density: Option<f32>,
+	pub axle_mass	: Option<f32>,
+	pub axle_material: Option<MaterialDesc>,
+}
+
+impl Default for WheelDesc {
+	fn default() -> Self {
+		Self {
+			name		: "Wheel".to_string(),
+			offset		: Vec3::ZERO,
+			radius		: 0.8,
+			half_width	: 0.5,
+			density		: 2.0,
+			mass		: None,
+			material	: MaterialDesc::default(),
+			front		: false,
+			steered		: false,
+			driven		: false,
+			tire		: TireDesc::default(),
+			axle_density: None,
+			axle_mass	: None,
+			axle_material: None,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct JointsDesc {
+	// axle joint holds steering position with these
+	pub axle_stiffness	: f32,
+	pub axle_damping	: f32,
+
+	// prismatic joint between body and axle acting as a spring. Rest length is the distance from mount point to axle
+	pub suspension_stiffness	: f32,
+	pub suspension_damping		: f32,
+	pub suspension_rest_length	: f32,
+	pub suspension_travel		: f32,
+
+	pub steer_angle		: f32,
+	pub steer_stiffness	: f32,
+	pub steer_damping	: f32,
+	// 0 parallel steering, 100 ideal Ackermann, negative for anti-Ackermann
+	pub ackermann_percent: f32,
+
+	// wheel motor gain. Engine limits motor torque, so keep it high enough for the limit to matter
+	pub drive_factor	: f32,
+}
+
+impl Default for JointsDesc {
+	fn default() -> Self {
+		Self {
+			axle_stiffness	: 10.0,
+			axle_damping	: 3.0,
+
+			suspension_stiffness	: 100.0,
+			suspension_damping		: 10.0,
+			suspension_rest_length	: 0.3,
+			suspension_travel		: 0.2,
+
+			steer_angle		: 20.0,
+			steer_stiffness	: 5.0,
+			steer_damping	: 3.0,
+			ackermann_percent: 100.0,
+
+			drive_factor	: 10.0,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct VehicleDesc {
+	pub body		: BodyDesc,
+	pub axle		: AxleDesc,
+	pub wheels		: Vec<WheelDesc>,
+	pub joints		: JointsDesc,
+	pub drivetrain	: Drivetrain,
+	pub engine		: Engine,
+	pub gearbox		: Gearbox,
+	// initial setting for every axle
+	pub differential: DiffSettings,
+	// settings of axles in the order they first appear in wheels, axles past the end get differential
+	pub differentials: Vec<DiffSettings>,
+	pub brakes		: Brakes,
+	pub driving_aids: DrivingAids,
+	pub input		: InputConfig,
+}
+
+impl Default for VehicleDesc {
+	fn default() -> Self {
+		// offsets for wheel placement relative to body center
+		let x_off = 0.8;
+		let y_off = 0.8;
+		let z_off = 1.4;
+
+		let wheel = |name: &str, offset: Vec3, front: bool| WheelDesc {
+			name	: name.to_string(),
+			offset,
+			front,
+			steered	: front,
+			driven	: !front,
+			..WheelDesc::default()
+		};
+
+		Self {
+			body	: BodyDesc::default(),
+			axle	: AxleDesc::default(),
+			wheels	: vec![
+				wheel("RF", Vec3::new( x_off, -y_off,  z_off), true),
+				wheel("LF", Vec3::new(-x_off, -y_off,  z_off), true),
+				wheel("RR", Vec3::new( x_off, -y_off, -z_off), false),
+				wheel("LR", Vec3::new(-x_off, -y_off, -z_off), false),
+			],
+			joints	: JointsDesc::default(),
+			drivetrain: Drivetrain::default(),
+			engine	: Engine::default(),
+			gearbox	: Gearbox::default(),
+			differential: DiffSettings::default(),
+			differentials: Vec::new(),
+			brakes	: Brakes::default(),
+			driving_aids: DrivingAids::default(),
+			input	: InputConfig::default(),
+		}
+	}
+}
+
+impl VehicleDesc {
+	pub fn load(path: &Path) -> Result<Self, String> {
+		load_ron(path)
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), String> {
+		let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new()).map_err(|e| format!("failed to serialize vehicle: {}", e))?;
+		if let Some(dir) = path.parent() {
+			std::fs::create_dir_all(dir).map_err(|e| format!("failed to create {:?}: {}", dir, e))?;
+		}
+		std::fs::write(path, text).map_err(|e| format!("failed to write {:?}: {}", path, e))
+	}
+
+	// falls back to default vehicle if file can't be loaded so that a typo in a file doesn't take the whole app down
+	pub fn load_or_default(path: Option<&Path>) -> Self {
+		match path {
+			Some(path) => match Self::load(path) {
+				Ok(desc) => {
+					println!("loaded vehicle description from {:?}", path);
+					desc
+				},
+				Err(err) => {
+					println!("{}. Using default vehicle", err);
+					Self::default()
+				},
+			},
+			None => Self::default(),
+		}
+	}
+
+	pub fn first_wheel(&self, front: bool) -> Option<&WheelDesc> {
+		self.wheels.iter().find(|w| w.front == front)
+	}
+}